@@ -2,6 +2,7 @@ use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 use turn_based_games::model;
 use turn_based_games::model::{GameState, MiniMaxGameState, MoveIterator, MoveOutcome, Player};
+use turn_based_games::tuning::{Parameters, Tunable};
 
 #[derive(PartialOrd, Ord, PartialEq, Eq, Clone, Copy, Debug, EnumIter)]
 enum ChessPiece {
@@ -137,6 +138,8 @@ struct ChessGameState {
     positions: [BoardPosition; 32],
     player1_castling_state: CastlingState,
     player2_castling_state: CastlingState,
+    // The values of pawns, knights, bishops, rooks and queens, see Tunable
+    piece_values: [i64; 5],
 }
 
 impl ChessGameState {
@@ -245,6 +248,7 @@ impl ChessGameState {
             positions,
             player1_castling_state: CastlingState::new(),
             player2_castling_state: CastlingState::new(),
+            piece_values: PIECE_VALUES,
         }
     }
 
@@ -648,42 +652,73 @@ impl MoveIterator for ChessMoveIterator {
     }
 }
 
+static PIECE_VALUES: [i64; 5] = [100, 350, 350, 525, 1000];
+const PIECE_VALUE_NAMES: [&str; 5] = [
+    "PAWN_VALUE",
+    "KNIGHT_VALUE",
+    "BISHOP_VALUE",
+    "ROOK_VALUE",
+    "QUEEN_VALUE",
+];
+
 impl MiniMaxGameState for ChessGameState {
     fn evaluate(self: &Self, player: &Player) -> i64 {
-        static PAWN_VALUE: i64 = 100;
-        static KNIGHT_VALUE: i64 = 350;
-        static BISHOP_VALUE: i64 = 350;
-        static ROOK_VALUE: i64 = 525;
-        static QUEEN_VALUE: i64 = 1000;
+        self.piece_values
+            .iter()
+            .zip(self.material_counts(player))
+            .map(|(value, count)| value * count)
+            .sum()
+    }
+}
+
+impl Tunable for ChessGameState {
+    fn parameters(&self) -> Parameters {
+        let named: Vec<(&str, i64)> = PIECE_VALUE_NAMES
+            .iter()
+            .copied()
+            .zip(self.piece_values)
+            .collect();
+        Parameters::new(&named)
+    }
 
-        let mut white_score: i64 = 0;
-        let mut black_score: i64 = 0;
+    // Parameters are looked up by name, piece values without a parameter keep their value
+    fn set_parameters(&mut self, parameters: &Parameters) {
+        for (value, name) in self.piece_values.iter_mut().zip(PIECE_VALUE_NAMES) {
+            if let Some(parameter) = parameters.get(name) {
+                *value = parameter;
+            }
+        }
+    }
 
+    fn features(&self, player: &Player) -> Vec<i64> {
+        self.material_counts(player).to_vec()
+    }
+}
+
+impl ChessGameState {
+    // Number of pawns, knights, bishops, rooks and queens of the player, minus those of the
+    // opponent
+    fn material_counts(&self, player: &Player) -> [i64; 5] {
+        let mut counts = [0; 5];
         for (index, piece) in ChessPiece::iter().enumerate() {
             if let Some(p) = self.positions.get(index) {
                 if on_board(*p) {
                     use ChessPieceType::*;
-                    let value = match piece.piece_type() {
-                        Pawn => PAWN_VALUE,
-                        Knight => KNIGHT_VALUE,
-                        Bishop => BISHOP_VALUE,
-                        Rook => ROOK_VALUE,
-                        Queen => QUEEN_VALUE,
-                        King => 0,
+                    let value_index = match piece.piece_type() {
+                        Pawn => 0,
+                        Knight => 1,
+                        Bishop => 2,
+                        Rook => 3,
+                        Queen => 4,
+                        King => continue,
                         NoType => panic!("unreachable code!"),
                     };
-                    if piece.is_white() {
-                        white_score += value;
-                    } else {
-                        black_score += value;
-                    }
+                    let own = piece.is_white() == (*player == Player::Player1);
+                    counts[value_index] += if own { 1 } else { -1 };
                 }
             }
         }
-        match player {
-            Player::Player1 => white_score - black_score,
-            Player::Player2 => black_score - white_score,
-        }
+        counts
     }
 }
 
@@ -693,6 +728,7 @@ fn main() {}
 mod test {
     use super::*;
     use turn_based_games::model::GameState;
+    use turn_based_games::testing::{check_invariants, perft};
 
     // Helper function
     fn find_move_by_positions(gs: &ChessGameState, start: usize, end: usize) -> Option<ChessMove> {
@@ -1128,4 +1164,46 @@ mod test {
         assert_eq!(white_score, 4250);
         assert_eq!(black_score, -4250);
     }
+
+    #[test]
+    fn tune_piece_values() {
+        // Setup
+        let mut gs = ChessGameState::new();
+        gs.apply_move(&find_move_by_positions(&gs, 12, 28).unwrap());
+        gs.apply_move(&find_move_by_positions(&gs, 51, 35).unwrap());
+        gs.apply_move(&find_move_by_positions(&gs, 28, 35).unwrap()); // White beats a pawn
+
+        // Act
+        gs.set_parameters(&Parameters::new(&[("PAWN_VALUE", 80), ("UNKNOWN", 1)]));
+
+        // Test
+        assert_eq!(gs.features(&Player::Player1), vec![1, 0, 0, 0, 0]);
+        assert_eq!(gs.parameters().get("PAWN_VALUE"), Some(80));
+        assert_eq!(gs.parameters().get("QUEEN_VALUE"), Some(1000));
+        assert_eq!(gs.evaluate(&Player::Player1), 80);
+        assert_eq!(gs.evaluate(&Player::Player2), -80);
+    }
+
+    #[test]
+    fn perft_from_start_position() {
+        // Setup
+        let mut gs = ChessGameState::new();
+
+        // Act & Test
+        assert_eq!(perft(&mut gs, 1), 20);
+        assert_eq!(perft(&mut gs, 2), 400);
+    }
+
+    #[test]
+    fn apply_reverse_invariants_from_start_position() {
+        // Setup
+        let mut gs = ChessGameState::new();
+
+        // Act
+        let result = check_invariants(&mut gs, 3);
+
+        // Test
+        assert!(result.is_ok(), "{}", result.unwrap_err());
+        assert_eq!(gs, ChessGameState::new());
+    }
 }
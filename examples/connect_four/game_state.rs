@@ -1,19 +1,50 @@
+use std::hash::{Hash, Hasher};
 use turn_based_games::model::{GameState, MiniMaxGameState, MoveOutcome, Player};
+use turn_based_games::opening_book::BookPosition;
+use turn_based_games::tablebase::PositionIndex;
+use turn_based_games::tools::bitboard::BitBoard64;
 use turn_based_games::tools::grid_board;
-use turn_based_games::tools::grid_board::BoardPosition;
+use turn_based_games::tools::grid_board::{BoardPosition, ColumnLetters};
+use turn_based_games::tools::grid_board::text::{FieldSymbol, TextFormat};
+use turn_based_games::tuning::{Parameters, Tunable};
 use crate::r#move::{Move, MoveIterator};
 
+#[derive(Clone)]
 pub struct ConnectFourGameState {
     active_player: Player,
     board: grid_board::Board<ConnectFourField>,
-    col_heights: [usize;7],
+    col_heights: Vec<usize>,
+    // The pieces of Player1 and Player2, to find four in a row quickly
+    pieces: [BitBoard64; 2],
+    // The scores of L1 to L4 opportunities, see Tunable
+    weights: [i64; 4],
 }
 
+#[derive(Clone, Copy, PartialEq)]
 pub enum ConnectFourField {
     Empty,
     Piece(Player)
 }
 
+impl FieldSymbol for ConnectFourField {
+    fn symbol(&self) -> char {
+        match self {
+            ConnectFourField::Empty => '.',
+            ConnectFourField::Piece(Player::Player1) => 'X',
+            ConnectFourField::Piece(Player::Player2) => 'O',
+        }
+    }
+
+    fn from_symbol(symbol: char) -> Option<Self> {
+        match symbol {
+            '.' => Some(ConnectFourField::Empty),
+            'X' => Some(ConnectFourField::Piece(Player::Player1)),
+            'O' => Some(ConnectFourField::Piece(Player::Player2)),
+            _ => None,
+        }
+    }
+}
+
 #[derive(PartialEq, Debug, Clone)]
 pub enum OpportunityType {
     L0,
@@ -28,58 +59,42 @@ static L2_SCORE: i64 = 1 << 8;
 static L3_SCORE: i64 = 1 << 16;
 static L4_SCORE: i64 = 1 << 24;
 
+// The board size covered by the PositionIndex, small enough to be solved completely
+const SMALL_COLUMNS: usize = 4;
+const SMALL_ROWS: usize = 4;
+
 impl ConnectFourGameState {
     pub fn new() -> ConnectFourGameState {
+        ConnectFourGameState::with_size(7, 6)
+    }
+
+    // An empty board of a different size, still connecting four. Panics if it does not fit into
+    // a BitBoard64.
+    pub fn with_size(columns: usize, rows: usize) -> ConnectFourGameState {
         ConnectFourGameState {
             active_player: Player::Player1,
-            board: grid_board::Board::new(6, 7, || ConnectFourField::Empty),
-            col_heights: [0;7],
+            board: grid_board::Board::new(rows, columns, || ConnectFourField::Empty),
+            col_heights: vec![0; columns],
+            pieces: [BitBoard64::new(rows, columns); 2],
+            weights: [L1_SCORE, L2_SCORE, L3_SCORE, L4_SCORE],
         }
     }
 
-    pub fn print_as_ascii(&self) {
-        let mut output = [[' ';15];7];
-        for i in 0..6 {
-            output[i][0] = '|';
-            output[i][14] = '|';
-        }
-        output[6][0] = '+';
-        output[6][14] = '+';
-        for i in 1..14 {
-            output[6][i] = '-';
-        }
-
-        for x in 0..7 {
-            for y in 0..6 {
-                output[5-y][2*x+1] = match self.board.get(
-                    &BoardPosition::new(x as isize, y as isize)
-                ) {
-                    None => {
-                        ' '
-                    }
-                    Some(ConnectFourField::Empty) => {
-                        ' '
-                    }
-                    Some(ConnectFourField::Piece(Player::Player1)) => {
-                        'X'
-                    }
-                    Some(ConnectFourField::Piece(Player::Player2)) => {
-                        'O'
-                    }
-                }
-            }
+    fn pieces_mut(&mut self, player: Player) -> &mut BitBoard64 {
+        match player {
+            Player::Player1 => &mut self.pieces[0],
+            Player::Player2 => &mut self.pieces[1],
         }
+    }
 
-        for l in 0..7 {
-            let line: String = output[l].iter().collect();
-            println!("{}", line);
-        }
+    pub fn print_as_ascii(&self) {
+        print!("{}", self.board.to_text(&TextFormat::new()));
     }
 
     fn is_tie(&self) -> bool {
         // No win, is it a tie? No, if any row as free fields.
         for col_height in &self.col_heights {
-            if *col_height < 6 {
+            if *col_height < self.board.rows() {
                 return false;
             }
         }
@@ -87,7 +102,7 @@ impl ConnectFourGameState {
     }
 
     pub fn valid_move(self: &Self, m: &Move) -> bool {
-        m.col < 7 && self.col_heights[m.col as usize] < 6
+        m.col < self.board.columns() && self.col_heights[m.col as usize] < self.board.rows()
     }
 
     fn best_opportunity_for_field(&self, pos: BoardPosition, player: Player) -> OpportunityType {
@@ -160,10 +175,10 @@ impl ConnectFourGameState {
     fn points_for_opportunity(&self, oppertunity: &OpportunityType) -> i64 {
         match oppertunity {
             OpportunityType::L0 => 0,
-            OpportunityType::L1 => L1_SCORE,
-            OpportunityType::L2 => L2_SCORE,
-            OpportunityType::L3 => L3_SCORE,
-            OpportunityType::L4 => L4_SCORE,
+            OpportunityType::L1 => self.weights[0],
+            OpportunityType::L2 => self.weights[1],
+            OpportunityType::L3 => self.weights[2],
+            OpportunityType::L4 => self.weights[3],
         }
     }
 
@@ -189,6 +204,67 @@ impl ConnectFourGameState {
         }
         res
     }
+
+    // The pieces of a column with a leading 1 above them, the bits of Player1 pieces are set
+    fn column_code(&self, col: usize) -> u64 {
+        let mut code = 1;
+        for row in (0..self.col_heights[col]).rev() {
+            code <<= 1;
+            if let Some(ConnectFourField::Piece(Player::Player1)) = self.board.get(&BoardPosition::new(col as isize, row as isize)) {
+                code |= 1;
+            }
+        }
+        code
+    }
+}
+
+// Two states are the same, if the pieces are (the active player follows from them)
+impl PartialEq for ConnectFourGameState {
+    fn eq(&self, other: &Self) -> bool {
+        self.board.rows() == other.board.rows()
+            && self.col_heights.len() == other.col_heights.len()
+            && (0..self.col_heights.len()).all(|col| self.column_code(col) == other.column_code(col))
+    }
+}
+
+impl Eq for ConnectFourGameState {}
+
+impl Hash for ConnectFourGameState {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        for col in 0..self.col_heights.len() {
+            self.column_code(col).hash(state);
+        }
+    }
+}
+
+// Only boards of the small size are indexed, larger ones are not solvable anyway
+impl PositionIndex for ConnectFourGameState {
+    fn position_count() -> u64 {
+        let column_codes = (1 << (SMALL_ROWS + 1)) - 1;
+        (column_codes as u64).pow(SMALL_COLUMNS as u32)
+    }
+
+    fn position_index(&self) -> Option<u64> {
+        if self.board.columns() != SMALL_COLUMNS || self.board.rows() != SMALL_ROWS {
+            return None;
+        }
+        let column_codes = (1 << (SMALL_ROWS + 1)) - 1;
+        Some((0..SMALL_COLUMNS).fold(0, |index, col| index * column_codes + self.column_code(col) - 1))
+    }
+}
+
+// Moves are named by the column letter, like a column in chess
+impl BookPosition for ConnectFourGameState {
+    // FNV-1a over the column codes, so it does not change between program runs
+    fn position_hash(&self) -> u64 {
+        (0..self.col_heights.len()).fold(0xcbf2_9ce4_8422_2325u64, |hash, col| {
+            (hash ^ self.column_code(col)).wrapping_mul(0x0100_0000_01b3)
+        })
+    }
+
+    fn move_notation(&self, m: &Move) -> String {
+        ColumnLetters::Chess.label(m.col as isize).unwrap()
+    }
 }
 
 impl GameState for ConnectFourGameState {
@@ -201,7 +277,7 @@ impl GameState for ConnectFourGameState {
 
     fn move_iterator(self: &Self) -> Self::MoveIterator {
         let mut ms = Vec::new();
-        for col in 0..7 {
+        for col in 0..self.board.columns() {
             if self.valid_move(&Move{col, opp_type: OpportunityType::L0}) {
                 ms.push(Move{
                     col,
@@ -222,13 +298,16 @@ impl GameState for ConnectFourGameState {
     fn apply_move(self: &mut Self, m: &Self::Move) -> MoveOutcome {
         let insert_pos = BoardPosition::new(m.col as isize, self.col_heights[m.col as usize] as isize);
 
-        self.board.set(&insert_pos, ConnectFourField::Piece(self.active_player));
+        let player = self.active_player;
+        self.board.set(&insert_pos, ConnectFourField::Piece(player));
+        let pieces = self.pieces_mut(player);
+        pieces.set(&insert_pos, true);
+        let won = pieces.has_line(4);
         self.col_heights[m.col as usize] += 1;
-        self.active_player = self.active_player.other();
+        self.active_player = player.other();
 
-        // Look for win
-        if m.opp_type == OpportunityType::L3 || m.opp_type == OpportunityType::L4 {
-            return MoveOutcome::PlayerWon(self.active_player.other());
+        if won {
+            return MoveOutcome::PlayerWon(player);
         }
 
         // No win, is it a tie?
@@ -245,13 +324,48 @@ impl GameState for ConnectFourGameState {
         let remove_pos = BoardPosition::new(m.col as isize, self.col_heights[m.col as usize] as isize);
 
         self.board.set(&remove_pos, ConnectFourField::Empty);
+        let player = self.active_player;
+        self.pieces_mut(player).set(&remove_pos, false);
     }
 }
 
 impl MiniMaxGameState for ConnectFourGameState {
     fn evaluate(self: &Self, player: &Player) -> i64 {
-        let mut p1_points = 0;
-        let mut p2_points = 0;
+        self.weights
+            .iter()
+            .zip(self.opportunity_counts(player))
+            .map(|(weight, count)| weight * count)
+            .sum()
+    }
+}
+
+const WEIGHT_NAMES: [&str; 4] = ["L1_SCORE", "L2_SCORE", "L3_SCORE", "L4_SCORE"];
+
+impl Tunable for ConnectFourGameState {
+    fn parameters(&self) -> Parameters {
+        let named: Vec<(&str, i64)> = WEIGHT_NAMES.iter().copied().zip(self.weights).collect();
+        Parameters::new(&named)
+    }
+
+    // Parameters are looked up by name, weights without a parameter keep their value
+    fn set_parameters(&mut self, parameters: &Parameters) {
+        for (weight, name) in self.weights.iter_mut().zip(WEIGHT_NAMES) {
+            if let Some(value) = parameters.get(name) {
+                *weight = value;
+            }
+        }
+    }
+
+    fn features(&self, player: &Player) -> Vec<i64> {
+        self.opportunity_counts(player).to_vec()
+    }
+}
+
+impl ConnectFourGameState {
+    // Number of L1 to L4 opportunities of the player, minus those of the opponent
+    fn opportunity_counts(&self, player: &Player) -> [i64; 4] {
+        let mut p1_counts = [0; 5];
+        let mut p2_counts = [0; 5];
         for col in 0..self.board.columns() {
             let mut last_p1_oppertunity = OpportunityType::L0;
             let mut last_p2_oppertunity = OpportunityType::L0;
@@ -260,31 +374,32 @@ impl MiniMaxGameState for ConnectFourGameState {
                 let p2_oppertunity = self.best_opportunity_for_field(BoardPosition::new(col as isize, i as isize), Player::Player2);
 
                 if p1_oppertunity == OpportunityType::L3 && last_p1_oppertunity == OpportunityType::L3 {
-                    p1_points += self.points_for_opportunity(&OpportunityType::L4);
+                    p1_counts[OpportunityType::L4 as usize] += 1;
                     break; // No need to look up this way, this will stop here
                 }
                 if p2_oppertunity == OpportunityType::L3 && last_p2_oppertunity == OpportunityType::L3 {
-                    p2_points += self.points_for_opportunity(&OpportunityType::L4);
+                    p2_counts[OpportunityType::L4 as usize] += 1;
                     break; // No need to look up this way, this will stop here
                 }
 
-                // Add the opportunity points!
+                // Count the opportunities!
                 if last_p2_oppertunity != OpportunityType::L3 { // Skip if opponent wold have won!
-                    p1_points += self.points_for_opportunity(&p1_oppertunity);
+                    p1_counts[p1_oppertunity.clone() as usize] += 1;
                 }
                 if last_p1_oppertunity != OpportunityType::L3 { // Skip if opponent wold have won!
-                    p2_points += self.points_for_opportunity(&p2_oppertunity);
+                    p2_counts[p2_oppertunity.clone() as usize] += 1;
                 }
 
                 last_p1_oppertunity = p1_oppertunity;
                 last_p2_oppertunity = p2_oppertunity;
             }
         }
-        if *player == Player::Player1 {
-            p1_points - p2_points
+        let (own, other) = if *player == Player::Player1 {
+            (p1_counts, p2_counts)
         } else {
-            p2_points - p1_points
-        }
+            (p2_counts, p1_counts)
+        };
+        [1, 2, 3, 4].map(|i| own[i] - other[i])
     }
 }
 
@@ -292,6 +407,17 @@ impl MiniMaxGameState for ConnectFourGameState {
 mod test {
     use more_asserts::*;
     use turn_based_games::model::MoveIterator;
+    use turn_based_games::opening_book::OpeningBookBuilder;
+    use turn_based_games::tournament::{ki_battle_with_options, MatchOptions};
+    use turn_based_games::tuning::{Tuner, TuningData};
+    use turn_based_games::learning::FeatureExtractor;
+    use turn_based_games::learning::alpha_zero::{compare_models, self_play, PolicyFeatures, SelfPlayOptions};
+    use turn_based_games::learning::policy_value::{PolicyValueMlp, PolicyValueModel};
+    use turn_based_games::tools::random::Random;
+    use turn_based_games::best_first::{b_star, sss_star};
+    use turn_based_games::negamax::{negamax, negamax_with_statistics, negamax_with_tablebase};
+    use turn_based_games::solver::{solve, GameValue};
+    use turn_based_games::tablebase::{Tablebase, TablebaseProbe};
     use super::*;
 
     fn find_move_by_col(gs: &ConnectFourGameState, col: usize) -> Option<Move> {
@@ -492,4 +618,225 @@ mod test {
         // -3 of lenght 1 (from opponent
         assert_eq!(gs.evaluate(&Player::Player2), -L3_SCORE + 16 * L1_SCORE);
     }
+
+    #[test]
+    fn diagonal_four_wins() {
+        // Setup
+        let mut gs = ConnectFourGameState::new();
+        for col in [0, 1, 1, 2, 2, 3, 2, 3, 3, 6] {
+            gs.apply_move(&find_move_by_col(&gs, col).unwrap());
+        }
+        // Now it should look like this:
+        //     X X
+        //   X X O
+        // X O O O _ _ O
+
+        // Act
+        let outcome = gs.apply_move(&Move { col: 3, opp_type: OpportunityType::L0 });
+
+        // Test
+        assert_eq!(outcome, MoveOutcome::PlayerWon(Player::Player1));
+        gs.reverse_move(&Move { col: 3, opp_type: OpportunityType::L0 });
+        assert_eq!(gs.apply_move(&Move { col: 4, opp_type: OpportunityType::L0 }),
+                   MoveOutcome::SwitchPlayer(Player::Player2));
+    }
+
+    #[test]
+    fn best_first_search_statistics() {
+        // Setup
+        let mut gs = ConnectFourGameState::new();
+        for col in [3, 3, 2] {
+            gs.apply_move(&find_move_by_col(&gs, col).unwrap());
+        }
+        let initial = gs.clone();
+
+        // Act
+        let (negamax_score, _, negamax_statistics) = negamax_with_statistics(&mut gs, 4);
+        let (sss_score, sss_move, sss_statistics) = sss_star(&mut gs, 4);
+        let (_, b_star_move, b_star_statistics) = b_star(&mut gs, 4, L3_SCORE, 10_000);
+
+        // Test
+        assert_eq!(sss_score, negamax_score);
+        let sss_move = sss_move.unwrap();
+        gs.apply_move(&sss_move);
+        assert_eq!(-negamax(&mut gs, 3).0, negamax_score);
+        gs.reverse_move(&sss_move);
+        // SSS* never evaluates more leaves than alpha-beta, B* stops expanding once the best
+        // move is separated
+        assert_le!(sss_statistics.evaluations, negamax_statistics.evaluations);
+        assert_le!(sss_statistics.nodes, negamax_statistics.nodes);
+        assert!(gs.valid_move(&b_star_move.unwrap()));
+        assert_gt!(b_star_statistics.nodes, 0);
+        assert_lt!(b_star_statistics.nodes, negamax_statistics.nodes);
+        assert!(gs == initial);
+    }
+
+    #[test]
+    fn tune_opportunity_scores() {
+        // Setup
+        let mut data = TuningData::new();
+        for seed in 0..4 {
+            let mut options = MatchOptions::new(2, 2);
+            options.seed = seed;
+            options.random_opening_plies = 4;
+            let result = ki_battle_with_options(
+                &mut ConnectFourGameState::new(),
+                &mut ConnectFourGameState::new(),
+                |m, gs| find_move_by_col(gs, m.col),
+                |m, gs| find_move_by_col(gs, m.col),
+                &options,
+            );
+            data.add_match_result(&mut ConnectFourGameState::new(), &result);
+        }
+        let parameters = ConnectFourGameState::new().parameters();
+        let mut tuner = Tuner::new();
+        tuner.fit_scaling(&data, &parameters);
+        tuner.max_rounds = 10;
+
+        // Act
+        let tuned = tuner.tune(&data, &parameters);
+
+        // Test
+        assert_gt!(data.len(), 0);
+        assert_le!(tuner.error(&data, &tuned), tuner.error(&data, &parameters));
+        let mut gs = ConnectFourGameState::new();
+        gs.set_parameters(&tuned);
+        assert_eq!(gs.parameters(), tuned);
+    }
+
+    #[test]
+    fn set_parameters_by_name() {
+        // Setup
+        let mut gs = ConnectFourGameState::new();
+
+        // Act
+        gs.set_parameters(&Parameters::new(&[("L3_SCORE", 7), ("UNKNOWN", 1)]));
+
+        // Test
+        assert_eq!(gs.parameters().get("L3_SCORE"), Some(7));
+        assert_eq!(gs.parameters().get("L1_SCORE"), Some(L1_SCORE));
+    }
+
+    #[test]
+    fn play_with_book_from_recorded_games() {
+        // Setup
+        let mut builder = OpeningBookBuilder::new(4);
+        for seed in 0..4 {
+            let mut options = MatchOptions::new(2, 2);
+            options.seed = seed;
+            options.random_opening_plies = 2;
+            let result = ki_battle_with_options(
+                &mut ConnectFourGameState::new(),
+                &mut ConnectFourGameState::new(),
+                |m, gs| find_move_by_col(gs, m.col),
+                |m, gs| find_move_by_col(gs, m.col),
+                &options,
+            );
+            builder.add_game(&ConnectFourGameState::new(), &result.moves, result.winner);
+        }
+        let book = builder.build();
+        let initial = ConnectFourGameState::new();
+        let mut options = MatchOptions::new(2, 2);
+        options.ki1_book = Some(&book);
+
+        // Act
+        let result = ki_battle_with_options(
+            &mut ConnectFourGameState::new(),
+            &mut ConnectFourGameState::new(),
+            |m, gs| find_move_by_col(gs, m.col),
+            |m, gs| find_move_by_col(gs, m.col),
+            &options,
+        );
+
+        // Test
+        assert_gt!(book.len(), 0);
+        assert_gt!(result.ki1_statistics.book_moves, 0);
+        let first_move = initial.move_notation(&result.moves[0]);
+        assert!(book.entries(initial.position_hash()).iter().any(|e| e.notation == first_move));
+    }
+
+    #[test]
+    fn play_small_board_perfect_with_tablebase() {
+        // Setup
+        let mut gs = ConnectFourGameState::with_size(SMALL_COLUMNS, SMALL_ROWS);
+        let solved = solve(&gs);
+        for col in [1, 2, 1, 2, 1] {
+            gs.apply_move(&find_move_by_col(&gs, col).unwrap());
+        }
+
+        // Act
+        let tablebase = Tablebase::from_solved(&solved);
+        let (_s, best_move) = negamax_with_tablebase(&mut gs, 1, &tablebase);
+
+        // Test
+        for (state, value) in solved.iter() {
+            assert_eq!(tablebase.probe(state), Some(value));
+        }
+        assert_eq!(tablebase.probe(&ConnectFourGameState::new()), None);
+        // O must block the column of X
+        //
+        // X
+        // X O
+        // X O _ _
+        assert_eq!(solved.value(&gs), Some(GameValue::Draw(11)));
+        let best_move = best_move.unwrap();
+        assert_eq!(best_move.col, 1);
+        assert_eq!(solved.move_value(&mut gs, &best_move), solved.value(&gs));
+    }
+
+    // The pieces, 1 for the player and -1 for the opponent, and one policy entry per column
+    struct ConnectFourFeatures;
+
+    impl FeatureExtractor<ConnectFourGameState> for ConnectFourFeatures {
+        fn feature_count(&self) -> usize {
+            42
+        }
+
+        fn features(&self, gs: &ConnectFourGameState, player: &Player) -> Vec<f64> {
+            let mut features = Vec::with_capacity(42);
+            for x in 0..7 {
+                for y in 0..6 {
+                    features.push(match gs.board.get(&BoardPosition::new(x, y)) {
+                        Some(ConnectFourField::Piece(p)) if p == player => 1.0,
+                        Some(ConnectFourField::Piece(_)) => -1.0,
+                        _ => 0.0,
+                    });
+                }
+            }
+            features
+        }
+    }
+
+    impl PolicyFeatures<ConnectFourGameState> for ConnectFourFeatures {
+        fn policy_size(&self) -> usize {
+            7
+        }
+
+        fn move_index(&self, _gs: &ConnectFourGameState, m: &Move) -> usize {
+            m.col
+        }
+    }
+
+    #[test]
+    fn alpha_zero_generation() {
+        // Setup
+        let mut random = Random::new(1);
+        let initial = PolicyValueMlp::new(42, 16, 7, &mut random);
+        let mut model = initial.clone();
+        let options = SelfPlayOptions::new(20);
+
+        // Act
+        let mut samples = Vec::new();
+        for _ in 0..2 {
+            let game = self_play(&mut ConnectFourGameState::new(), &ConnectFourFeatures, &model, &options, &mut random);
+            samples.extend(game.samples);
+        }
+        model.train_policy(&samples, 5, 0.01, &mut random);
+        let comparison = compare_models(ConnectFourGameState::new, &ConnectFourFeatures, &model, &initial, 2, 1, 2);
+
+        // Test
+        assert_gt!(samples.len(), 2 * 7);
+        assert_ne!(model, initial);
+        assert_eq!(comparison.wins + comparison.losses + comparison.ties, 2);
+    }
 }
@@ -1,15 +1,17 @@
 use turn_based_games::model::MoveOutcome::{PlayerWon, SwitchPlayer, Tie};
 use turn_based_games::model::{GameState, MiniMaxGameState, MoveOutcome, Player};
+use turn_based_games::tablebase::PositionIndex;
+use turn_based_games::tools::grid_board::{Board, BoardPosition};
 use turn_based_games::tournament;
 
-#[derive(PartialEq, Clone, Copy)]
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
 enum FieldState {
     EMPTY,
     CROSS,
     CIRCLE,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 struct Move {
     index: usize,
 }
@@ -34,7 +36,7 @@ impl turn_based_games::model::MoveIterator for MoveIterator {
 
     fn next(&mut self, gs: &Self::GameState) -> Option<&Self::Move> {
         for index in self.current_index..9 {
-            if gs.field[index] == FieldState::EMPTY {
+            if *gs.field(index) == FieldState::EMPTY {
                 self.current_index = index + 1;
                 self.current_move = Move { index };
                 return Some(&self.current_move);
@@ -45,21 +47,30 @@ impl turn_based_games::model::MoveIterator for MoveIterator {
     }
 }
 
+#[derive(PartialEq, Eq, Hash, Clone, Debug)]
 struct TicTacToeGameState {
     active_player: Player,
-    field: [FieldState; 9],
+    board: Board<FieldState>,
+    // Somebody has three in a row, it follows from the board
+    won: bool,
 }
 
 impl TicTacToeGameState {
     fn new() -> TicTacToeGameState {
         TicTacToeGameState {
             active_player: Player::Player1,
-            field: [FieldState::EMPTY; 9],
+            board: Board::new(3, 3, || FieldState::EMPTY),
+            won: false,
         }
     }
 
-    fn field_by_coords(self: &Self, x: usize, y: usize) -> &FieldState {
-        &self.field[y * 3 + x]
+    // The fields are numbered row by row, like the moves
+    fn position(index: usize) -> BoardPosition {
+        BoardPosition::new((index % 3) as isize, (index / 3) as isize)
+    }
+
+    fn field(&self, index: usize) -> &FieldState {
+        self.board.get(&Self::position(index)).unwrap()
     }
 }
 
@@ -72,7 +83,12 @@ impl GameState for TicTacToeGameState {
     }
 
     fn move_iterator(self: &Self) -> Self::MoveIterator {
-        MoveIterator::new()
+        let mut move_iterator = MoveIterator::new();
+        if self.won {
+            // No moves after the game ended
+            move_iterator.current_index = 9;
+        }
+        move_iterator
     }
 
     fn apply_move(self: &mut Self, m: &Self::Move) -> MoveOutcome {
@@ -84,39 +100,14 @@ impl GameState for TicTacToeGameState {
             Player::Player1 => FieldState::CROSS,
             Player::Player2 => FieldState::CIRCLE,
         };
-        self.field[m.index] = my_symbol;
-        let my_symbol = &self.field[m.index];
-        // Winner?
-        for x in 0..3 {
-            if self.field_by_coords(x, 0) == my_symbol
-                && self.field_by_coords(x, 1) == my_symbol
-                && self.field_by_coords(x, 2) == my_symbol
-            {
-                return PlayerWon(active_player);
-            }
-        }
-        for y in 0..3 {
-            if self.field_by_coords(0, y) == my_symbol
-                && self.field_by_coords(1, y) == my_symbol
-                && self.field_by_coords(2, y) == my_symbol
-            {
-                return PlayerWon(active_player);
-            }
-        }
-        if self.field_by_coords(0, 0) == my_symbol
-            && self.field_by_coords(1, 1) == my_symbol
-            && self.field_by_coords(2, 2) == my_symbol
-        {
-            return PlayerWon(active_player);
-        }
-        if self.field_by_coords(2, 0) == my_symbol
-            && self.field_by_coords(1, 1) == my_symbol
-            && self.field_by_coords(0, 2) == my_symbol
-        {
+        let pos = Self::position(m.index);
+        self.board.set(&pos, my_symbol);
+        if self.board.completes_line(&pos, 3, |f| *f == FieldState::EMPTY) {
+            self.won = true;
             return PlayerWon(active_player);
         }
 
-        if !self.field.contains(&FieldState::EMPTY) {
+        if (0..9).all(|index| *self.field(index) != FieldState::EMPTY) {
             return Tie;
         }
         return SwitchPlayer(self.active_player.clone());
@@ -124,7 +115,9 @@ impl GameState for TicTacToeGameState {
 
     fn reverse_move(self: &mut Self, m: &Self::Move) {
         self.active_player = self.active_player.other();
-        self.field[m.index] = FieldState::EMPTY;
+        self.board.set(&Self::position(m.index), FieldState::EMPTY);
+        // There are no moves after a win, so the game went on before
+        self.won = false;
     }
 }
 
@@ -134,6 +127,24 @@ impl MiniMaxGameState for TicTacToeGameState {
     }
 }
 
+impl PositionIndex for TicTacToeGameState {
+    fn position_count() -> u64 {
+        3u64.pow(9)
+    }
+
+    // The active player follows from the number of pieces, so the fields are enough
+    fn position_index(&self) -> Option<u64> {
+        Some((0..9).fold(0, |index, field| {
+            index * 3
+                + match self.field(field) {
+                    FieldState::EMPTY => 0,
+                    FieldState::CROSS => 1,
+                    FieldState::CIRCLE => 2,
+                }
+        }))
+    }
+}
+
 fn main() {
     fn find_move(m: &Move, gs: &TicTacToeGameState) -> Option<Move> {
         use turn_based_games::model::MoveIterator;
@@ -161,10 +172,18 @@ fn main() {
     }
 }
 
+// Run with `cargo test --example tictactoe --features proptest`
+#[cfg(all(test, feature = "proptest"))]
+turn_based_games::game_state_conformance_tests!(conformance, TicTacToeGameState::new(), 9);
+
 #[cfg(test)]
 mod test {
     use super::*;
-    use turn_based_games::negamax::negamax;
+    use turn_based_games::negamax::{negamax, negamax_with_tablebase};
+    use turn_based_games::proof_number::{pn2_search, pn_search, ProofResult};
+    use turn_based_games::solver::{solve, GameValue};
+    use turn_based_games::tablebase::Tablebase;
+    use turn_based_games::testing::perft;
 
     #[test]
     fn empty_board_should_npt_find_win_move() {
@@ -190,19 +209,106 @@ mod test {
 
         // Test
         assert_eq!(s, 0);
-        assert_eq!(best_move, Some(4));
+        assert_eq!(best_move, Some(Move { index: 4 }));
     }
 
     #[test]
     fn find_winning_move() {
-        // Running on board, where cross took the top left corner and circle the top right corner. There are now multiple winning moves!
+        // Running on board, where cross took the top left corner and circle the top edge next to it. Cross can force a win now!
         let mut tictactoe = TicTacToeGameState::new();
-        tictactoe.apply_move(&Move { index: 2 });
+        tictactoe.apply_move(&Move { index: 0 });
+        tictactoe.apply_move(&Move { index: 1 });
+        let solved = solve(&tictactoe);
 
         // Act
         let (s, best_move) = negamax(&mut tictactoe, 10);
 
         // Test
-        assert_eq!(s, i64::MAX - 1);
+        assert_eq!(s, i64::MAX - 2);
+        assert_eq!(
+            solved.move_value(&mut tictactoe, &best_move.unwrap()),
+            Some(GameValue::Win(5))
+        );
+    }
+
+    #[test]
+    fn perft_counts_games() {
+        // Setup
+        let mut tictactoe = TicTacToeGameState::new();
+
+        // Act & Test
+        assert_eq!(perft(&mut tictactoe, 1), 9);
+        assert_eq!(perft(&mut tictactoe, 2), 72);
+        assert_eq!(perft(&mut tictactoe, 5), 15120);
+        // Only games without a winner before the last field go to the full 9 moves
+        assert_eq!(perft(&mut tictactoe, 9), 127872);
+    }
+
+    #[test]
+    fn solve_tictactoe() {
+        // Setup
+        let mut tictactoe = TicTacToeGameState::new();
+
+        // Act
+        let solved = solve(&tictactoe);
+
+        // Test
+        // Reachable positions, without the 958 where the game is over
+        assert_eq!(solved.len(), 5478 - 958);
+        assert_eq!(solved.value(&tictactoe), Some(GameValue::Draw(9)));
+        tictactoe.apply_move(&Move { index: 0 });
+        tictactoe.apply_move(&Move { index: 1 });
+        // Circle answered the corner with an edge, cross can force a win now
+        assert_eq!(solved.value(&tictactoe), Some(GameValue::Win(5)));
+    }
+
+    #[test]
+    fn play_perfect_with_tablebase() {
+        // Setup
+        let mut tictactoe = TicTacToeGameState::new();
+        let solved = solve(&tictactoe);
+        let tablebase = Tablebase::from_solved(&solved);
+        tictactoe.apply_move(&Move { index: 0 });
+        tictactoe.apply_move(&Move { index: 1 });
+
+        // Act
+        // The evaluation of tic tac toe is always 0, only the tablebase knows better
+        let (_s, best_move) = negamax_with_tablebase(&mut tictactoe, 1, &tablebase);
+
+        // Test
+        let best_move = best_move.unwrap();
+        assert_eq!(
+            solved.move_value(&mut tictactoe, &best_move),
+            Some(GameValue::Win(5))
+        );
+    }
+
+    #[test]
+    fn prove_forced_win() {
+        // Setup
+        let mut tictactoe = TicTacToeGameState::new();
+        tictactoe.apply_move(&Move { index: 0 });
+        tictactoe.apply_move(&Move { index: 1 });
+        let solved = solve(&tictactoe);
+
+        // Act
+        let pn = pn_search(&mut tictactoe, 100_000);
+        let pn2 = pn2_search(&mut tictactoe, 100_000);
+
+        // Test
+        for search in [pn, pn2] {
+            match search.result {
+                ProofResult::Proven(m) => assert!(matches!(
+                    solved.move_value(&mut tictactoe, &m),
+                    Some(GameValue::Win(_))
+                )),
+                _ => panic!("The win should be proven"),
+            }
+        }
+        // From the empty board, there is no forced win
+        assert!(matches!(
+            pn_search(&mut TicTacToeGameState::new(), 100_000).result,
+            ProofResult::Disproven
+        ));
     }
 }
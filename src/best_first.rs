@@ -0,0 +1,511 @@
+/*! Best-first alternatives to [crate::negamax]: SSS* and B*.
+ *
+ * Both keep the explored tree in memory and report a [SearchStatistics], so their node
+ * efficiency can be compared with [crate::negamax::negamax_with_statistics]. The game state is
+ * moved through the tree by applying and reversing the moves on the path from the root.
+ */
+use crate::model::{MiniMaxGameState, MoveIterator, MoveOutcome, Player};
+use crate::negamax::{SearchStatistics, MAX_SCORE};
+
+// A node of a search tree, knowing its parent and the move leading to it
+trait TreeNode<M> {
+    fn link(&self) -> Option<(usize, &M)>;
+}
+
+// Moves the game state from the root to the node, returns the applied moves.
+fn go_to_node<GS, N>(gs: &mut GS, tree: &[N], node: usize) -> Vec<GS::Move>
+where
+    GS: MiniMaxGameState,
+    N: TreeNode<GS::Move>,
+{
+    let mut path = Vec::new();
+    let mut current = node;
+    while let Some((parent, m)) = tree[current].link() {
+        path.push(m.clone());
+        current = parent;
+    }
+    path.reverse();
+    for m in &path {
+        gs.apply_move(m);
+    }
+    path
+}
+
+fn go_to_root<GS: MiniMaxGameState>(gs: &mut GS, path: &[GS::Move]) {
+    for m in path.iter().rev() {
+        gs.reverse_move(m);
+    }
+}
+
+// The score of a move ending the game, for the given player
+fn terminal_score(outcome: MoveOutcome, player: Player) -> Option<i64> {
+    match outcome {
+        MoveOutcome::PlayerWon(p) if p == player => Some(MAX_SCORE),
+        MoveOutcome::PlayerWon(_) => Some(-MAX_SCORE),
+        MoveOutcome::Tie => Some(0),
+        MoveOutcome::SwitchPlayer(_) | MoveOutcome::ContinuePlayer(_) => None,
+    }
+}
+
+struct SssNode<M> {
+    link: Option<(usize, M)>,
+    children: Option<Vec<usize>>,
+    // Position in the children of the parent
+    child_index: usize,
+    // The root player is to move
+    max_node: bool,
+    depth: i8,
+    // Score for the root player, if the move to this node ended the game
+    terminal: Option<i64>,
+}
+
+impl<M> TreeNode<M> for SssNode<M> {
+    fn link(&self) -> Option<(usize, &M)> {
+        self.link.as_ref().map(|(p, m)| (*p, m))
+    }
+}
+
+#[derive(PartialEq, Clone, Copy)]
+enum Status {
+    Live,
+    Solved,
+}
+
+/** Stockman's SSS* algorithm (https://www.chessprogramming.org/SSS*_and_Dual*), searching to
+ * the given depth. It returns the same score as negamax, but never evaluates more leaves than
+ * alpha-beta with the same move order.
+ */
+pub fn sss_star<GS>(gs: &mut GS, depth: i8) -> (i64, Option<GS::Move>, SearchStatistics)
+where
+    GS: MiniMaxGameState,
+{
+    let root_player = gs.active_player();
+    let mut statistics = SearchStatistics::default();
+    let mut tree: Vec<SssNode<GS::Move>> = vec![SssNode {
+        link: None,
+        children: None,
+        child_index: 0,
+        max_node: true,
+        depth,
+        terminal: None,
+    }];
+    let mut open: Vec<(usize, Status, i64)> = vec![(0, Status::Live, MAX_SCORE + 1)];
+    let mut best_move = None;
+
+    loop {
+        // Take the state with the highest merit. On ties solved states go first, then the
+        // leftmost node, otherwise pruning would not happen.
+        let key = |(node, status, h): &(usize, Status, i64)| {
+            (*h, *status == Status::Solved, std::cmp::Reverse(*node))
+        };
+        let position = (0..open.len())
+            .max_by_key(|i| key(&open[*i]))
+            .expect("The open list is never empty before the root is solved");
+        let (node, status, h) = open.remove(position);
+
+        match status {
+            Status::Live => {
+                if let Some(score) = tree[node].terminal {
+                    open.push((node, Status::Solved, h.min(score)));
+                    continue;
+                }
+                if tree[node].depth > 0 && tree[node].children.is_none() {
+                    let children = expand_sss(gs, &mut tree, node, root_player);
+                    statistics.nodes += 1;
+                    tree[node].children = Some(children);
+                }
+                let children = tree[node].children.as_deref().unwrap_or(&[]);
+                if children.is_empty() {
+                    let path = go_to_node(gs, &tree, node);
+                    let score = gs.evaluate(&root_player);
+                    go_to_root(gs, &path);
+                    statistics.evaluations += 1;
+                    open.push((node, Status::Solved, h.min(score)));
+                } else if tree[node].max_node {
+                    for child in children {
+                        open.push((*child, Status::Live, h));
+                    }
+                } else {
+                    open.push((children[0], Status::Live, h));
+                }
+            }
+            Status::Solved => {
+                let parent = match &tree[node].link {
+                    None => return (h, best_move, statistics),
+                    Some((parent, _)) => *parent,
+                };
+                if tree[parent].max_node {
+                    // The parent is solved, forget about its other children
+                    let before = open.len();
+                    open.retain(|(n, _, _)| !is_descendant(&tree, *n, parent));
+                    statistics.cutoffs += (before - open.len()) as u64;
+                    open.push((parent, Status::Solved, h));
+                    if parent == 0 {
+                        best_move = tree[node].link.as_ref().map(|(_, m)| m.clone());
+                    }
+                } else {
+                    let siblings = tree[parent].children.as_deref().unwrap_or(&[]);
+                    match siblings.get(tree[node].child_index + 1) {
+                        Some(sibling) => open.push((*sibling, Status::Live, h)),
+                        None => open.push((parent, Status::Solved, h)),
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn expand_sss<GS: MiniMaxGameState>(
+    gs: &mut GS,
+    tree: &mut Vec<SssNode<GS::Move>>,
+    node: usize,
+    root_player: Player,
+) -> Vec<usize> {
+    let path = go_to_node(gs, tree, node);
+    let mut children = Vec::new();
+    let mut move_iterator = gs.move_iterator();
+    while let Some(m) = move_iterator.next(gs) {
+        let outcome = gs.apply_move(m);
+        let max_node = gs.active_player() == root_player;
+        gs.reverse_move(m);
+        tree.push(SssNode {
+            link: Some((node, m.clone())),
+            children: None,
+            child_index: children.len(),
+            max_node,
+            depth: tree[node].depth - 1,
+            terminal: terminal_score(outcome, root_player),
+        });
+        children.push(tree.len() - 1);
+    }
+    go_to_root(gs, &path);
+    children
+}
+
+fn is_descendant<M>(tree: &[SssNode<M>], node: usize, ancestor: usize) -> bool {
+    let mut current = node;
+    while let Some((parent, _)) = &tree[current].link {
+        if *parent == ancestor {
+            return true;
+        }
+        current = *parent;
+    }
+    false
+}
+
+struct BStarNode<M> {
+    link: Option<(usize, M)>,
+    children: Vec<usize>,
+    // The player to move changed with the move to this node
+    switched: bool,
+    depth: i8,
+    // Bounds of the value of the move to this node, for the player who made it
+    optimistic: i64,
+    pessimistic: i64,
+    expanded: bool,
+}
+
+impl<M> TreeNode<M> for BStarNode<M> {
+    fn link(&self) -> Option<(usize, &M)> {
+        self.link.as_ref().map(|(p, m)| (*p, m))
+    }
+}
+
+impl<M> BStarNode<M> {
+    fn is_exact(&self) -> bool {
+        self.optimistic == self.pessimistic
+    }
+}
+
+/** Berliner's B* algorithm (https://www.chessprogramming.org/B*), searching at most to the
+ * given depth and creating at most `max_nodes` nodes.
+ *
+ * Every node has an optimistic and a pessimistic bound of its value. For positions that are not
+ * at the depth limit, the bounds are the evaluation plus and minus `margin`. The search ends,
+ * when the pessimistic bound of the best move is not below the optimistic bounds of all other
+ * moves. It alternates between trying to raise the pessimistic bound of the best move
+ * (ProveBest) and to lower the optimistic bound of the best alternative (DisproveRest).
+ *
+ * Returns the pessimistic bound of the chosen move as score.
+ */
+pub fn b_star<GS>(
+    gs: &mut GS,
+    depth: i8,
+    margin: i64,
+    max_nodes: usize,
+) -> (i64, Option<GS::Move>, SearchStatistics)
+where
+    GS: MiniMaxGameState,
+{
+    let mut statistics = SearchStatistics::default();
+    let mut tree: Vec<BStarNode<GS::Move>> = vec![BStarNode {
+        link: None,
+        children: Vec::new(),
+        switched: false,
+        depth,
+        optimistic: MAX_SCORE,
+        pessimistic: -MAX_SCORE,
+        expanded: false,
+    }];
+    if depth > 0 {
+        expand_b_star(gs, &mut tree, 0, margin, &mut statistics);
+    }
+    let mut prove_best = true;
+
+    while tree.len() < max_nodes {
+        let root_children = &tree[0].children;
+        let best = match best_by(&tree, root_children, |n| (n.optimistic, n.pessimistic)) {
+            Some(best) => best,
+            None => break,
+        };
+        let alternative = best_by(
+            &tree,
+            &root_children
+                .iter()
+                .copied()
+                .filter(|c| *c != best)
+                .collect::<Vec<usize>>(),
+            |n| (n.optimistic, n.pessimistic),
+        );
+        let alternative = match alternative {
+            Some(alternative) if tree[alternative].optimistic > tree[best].pessimistic => {
+                alternative
+            }
+            // Separated, or the only move
+            _ => break,
+        };
+
+        let mut target = if prove_best { best } else { alternative };
+        if tree[target].is_exact() {
+            target = if target == best { alternative } else { best };
+        }
+        prove_best = !prove_best;
+
+        // Go down along the most optimistic moves
+        let mut node = target;
+        while tree[node].expanded && !tree[node].is_exact() {
+            node = best_by(&tree, &tree[node].children, |n| {
+                (n.optimistic, n.pessimistic)
+            })
+            .expect("Expanded nodes, that are not exact, have children");
+        }
+        if tree[node].is_exact() {
+            // Nothing to learn anymore, this only happens if the bounds were inconsistent
+            break;
+        }
+        expand_b_star(gs, &mut tree, node, margin, &mut statistics);
+
+        // Back up the bounds to the root
+        let mut current = node;
+        while let Some((parent, _)) = tree[current].link() {
+            update_bounds(&mut tree, current);
+            current = parent;
+        }
+    }
+
+    let best = best_by(&tree, &tree[0].children, |n| (n.pessimistic, n.optimistic));
+    match best {
+        Some(best) => (
+            tree[best].pessimistic,
+            tree[best].link.as_ref().map(|(_, m)| m.clone()),
+            statistics,
+        ),
+        None => {
+            statistics.evaluations += 1;
+            (gs.evaluate(&gs.active_player()), None, statistics)
+        }
+    }
+}
+
+// The node with the largest key, the first one on ties
+fn best_by<M, K: Ord>(
+    tree: &[BStarNode<M>],
+    nodes: &[usize],
+    key: fn(&BStarNode<M>) -> K,
+) -> Option<usize> {
+    nodes.iter().copied().fold(None, |best, n| match best {
+        Some(b) if key(&tree[b]) >= key(&tree[n]) => Some(b),
+        _ => Some(n),
+    })
+}
+
+fn expand_b_star<GS: MiniMaxGameState>(
+    gs: &mut GS,
+    tree: &mut Vec<BStarNode<GS::Move>>,
+    node: usize,
+    margin: i64,
+    statistics: &mut SearchStatistics,
+) {
+    statistics.nodes += 1;
+    let path = go_to_node(gs, tree, node);
+    let player = gs.active_player();
+    let child_depth = tree[node].depth - 1;
+    let mut move_iterator = gs.move_iterator();
+    while let Some(m) = move_iterator.next(gs) {
+        let outcome = gs.apply_move(m);
+        let switched = gs.active_player() != player;
+        let (optimistic, pessimistic) = match terminal_score(outcome, player) {
+            Some(score) => (score, score),
+            None => {
+                statistics.evaluations += 1;
+                let score = gs.evaluate(&player);
+                if child_depth <= 0 {
+                    (score, score)
+                } else {
+                    (score.saturating_add(margin), score.saturating_sub(margin))
+                }
+            }
+        };
+        gs.reverse_move(m);
+        tree.push(BStarNode {
+            link: Some((node, m.clone())),
+            children: Vec::new(),
+            switched,
+            depth: child_depth,
+            optimistic,
+            pessimistic,
+            expanded: terminal_score(outcome, player).is_some() || child_depth <= 0,
+        });
+        let child = tree.len() - 1;
+        tree[node].children.push(child);
+    }
+    go_to_root(gs, &path);
+    tree[node].expanded = true;
+    if tree[node].children.is_empty() {
+        // No moves: the evaluation is all we know
+        let center = tree[node].pessimistic / 2 + tree[node].optimistic / 2;
+        tree[node].optimistic = center;
+        tree[node].pessimistic = center;
+    }
+}
+
+fn update_bounds<M>(tree: &mut [BStarNode<M>], node: usize) {
+    if tree[node].children.is_empty() {
+        return;
+    }
+    // Bounds for the player to move in the node
+    let optimistic = tree[node]
+        .children
+        .iter()
+        .map(|c| tree[*c].optimistic)
+        .max();
+    let pessimistic = tree[node]
+        .children
+        .iter()
+        .map(|c| tree[*c].pessimistic)
+        .max();
+    let (optimistic, pessimistic) = (optimistic.unwrap(), pessimistic.unwrap());
+    // Bounds for the player who made the move to the node
+    if tree[node].switched {
+        tree[node].optimistic = -pessimistic;
+        tree[node].pessimistic = -optimistic;
+    } else {
+        tree[node].optimistic = optimistic;
+        tree[node].pessimistic = pessimistic;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::model::GameState;
+    use crate::negamax::{negamax, negamax_with_statistics};
+    use crate::testing::test_games::{NimGameState, TreeGameState};
+
+    // The negamax score of a move, for the player making it
+    fn move_score(gs: &mut TreeGameState, m: u32, depth: i8) -> i64 {
+        gs.apply_move(&m);
+        let score = -negamax(gs, depth - 1).0;
+        gs.reverse_move(&m);
+        score
+    }
+
+    #[test]
+    fn sss_star_finds_negamax_score() {
+        for branching in 2..5 {
+            for depth in 1..6 {
+                // Setup
+                let mut gs = TreeGameState::new(branching);
+
+                // Act
+                let (score, m, statistics) = sss_star(&mut gs, depth);
+                let (negamax_score, _, negamax_statistics) =
+                    negamax_with_statistics(&mut gs, depth);
+
+                // Test
+                assert_eq!(score, negamax_score);
+                assert_eq!(move_score(&mut gs, m.unwrap(), depth), negamax_score);
+                assert!(statistics.evaluations <= negamax_statistics.evaluations);
+                assert_eq!(gs, TreeGameState::new(branching));
+            }
+        }
+    }
+
+    #[test]
+    fn sss_star_with_game_ends() {
+        // Setup
+        let mut gs = NimGameState::new(7);
+
+        // Act
+        let (score, m, _) = sss_star(&mut gs, 8);
+
+        // Test
+        assert_eq!(score, MAX_SCORE);
+        assert_eq!(m, Some(1));
+    }
+
+    #[test]
+    fn b_star_finds_best_move() {
+        for branching in 2..5 {
+            for depth in 1..5 {
+                // Setup
+                let mut gs = TreeGameState::new(branching);
+
+                // Act
+                // The evaluation is between -100 and 100, so the bounds are always right
+                let (score, m, statistics) = b_star(&mut gs, depth, 200, 100_000);
+                let (negamax_score, _) = negamax(&mut gs, depth);
+
+                // Test
+                assert_eq!(move_score(&mut gs, m.unwrap(), depth), negamax_score);
+                assert!(score <= negamax_score);
+                assert!(statistics.nodes > 0);
+                assert_eq!(gs, TreeGameState::new(branching));
+            }
+        }
+    }
+
+    #[test]
+    fn all_algorithms_count_expanded_nodes() {
+        // Setup
+        let mut gs = TreeGameState::new(3);
+
+        // Act
+        let (_, _, negamax_statistics) = negamax_with_statistics(&mut gs, 2);
+        let (_, _, sss_statistics) = sss_star(&mut gs, 2);
+        let (_, _, b_star_statistics) = b_star(&mut gs, 2, 200, 100_000);
+
+        // Test
+        // Negamax and SSS* expand the root and its 3 children, B* may stop earlier
+        assert_eq!(negamax_statistics.nodes, 4);
+        assert_eq!(sss_statistics.nodes, 4);
+        assert!(b_star_statistics.nodes >= 1 && b_star_statistics.nodes <= 4);
+        assert_eq!(negamax_with_statistics(&mut gs, 1).2.nodes, 1);
+        assert_eq!(sss_star(&mut gs, 1).2.nodes, 1);
+        assert_eq!(b_star(&mut gs, 1, 200, 100_000).2.nodes, 1);
+    }
+
+    #[test]
+    fn b_star_with_game_ends() {
+        // Setup
+        let mut gs = NimGameState::new(7);
+
+        // Act
+        // Nim evaluates every position as 0, only the margin allows finding the win
+        let (_, m, _) = b_star(&mut gs, 8, MAX_SCORE, 100_000);
+
+        // Test
+        // B* stops as soon as the other move is known to lose, the win itself is not proven
+        assert_eq!(m, Some(1));
+    }
+}
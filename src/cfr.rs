@@ -0,0 +1,555 @@
+/*! Counterfactual regret minimization for games with imperfect information.
+ *
+ * In games like poker a player does not know the complete game state, only an information
+ * set: all the states the player can not tell apart. [InformationSetGame] describes such games,
+ * with chance moves (like dealing cards) and payoffs instead of a winner.
+ *
+ * [Cfr] computes an approximate Nash equilibrium with CFR
+ * (https://poker.cs.ualberta.ca/publications/NIPS07-cfr.pdf) or CFR+
+ * (https://arxiv.org/abs/1407.5042). How far a strategy is from an equilibrium is measured
+ * by its [exploitability].
+ */
+use std::collections::HashMap;
+use std::hash::Hash;
+use crate::model::Player;
+
+/** Who acts in a state of an [InformationSetGame] */
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Turn {
+    /// The player chooses a move, knowing only the information set
+    Player(Player),
+    /// A move is chosen at random, see [InformationSetGame::chance_moves]
+    Chance,
+    /// The game is over, see [InformationSetGame::utility]
+    Terminal,
+}
+
+/** A two player zero-sum game with imperfect information and chance moves.
+ *
+ * Like [crate::model::GameState], the state is changed by applying and reversing moves.
+ */
+pub trait InformationSetGame {
+    type Move: Clone;
+    /// Everything the player to move knows, the state must have perfect recall
+    type InformationSet: Clone + Eq + Hash;
+
+    fn turn(&self) -> Turn;
+
+    /** The moves of the player to move, always in the same order for an information set */
+    fn moves(&self) -> Vec<Self::Move>;
+
+    /** The possible chance moves with their probabilities, if it is the turn of chance */
+    fn chance_moves(&self) -> Vec<(Self::Move, f64)>;
+
+    /** The information set of the player to move */
+    fn information_set(&self) -> Self::InformationSet;
+
+    fn apply_move(&mut self, m: &Self::Move);
+    fn reverse_move(&mut self, m: &Self::Move);
+
+    /** The payoff of the player in a terminal state. The payoffs add up to 0. */
+    fn utility(&self, player: &Player) -> f64;
+}
+
+/** A mixed strategy: the probability of each move, for every information set */
+pub type Strategy<I> = HashMap<I, Vec<f64>>;
+
+/** The CFR solver, keeping the regrets and the average strategy of all information sets
+ * visited so far.
+ */
+pub struct Cfr<I> {
+    /// Use CFR+: regrets are never negative and later iterations have more weight in the
+    /// average strategy
+    pub plus: bool,
+    regrets: HashMap<I, Vec<f64>>,
+    strategy_sums: HashMap<I, Vec<f64>>,
+    iterations: u64,
+}
+
+impl<I: Clone + Eq + Hash> Cfr<I> {
+    pub fn new() -> Cfr<I> {
+        Cfr {
+            plus: false,
+            regrets: HashMap::new(),
+            strategy_sums: HashMap::new(),
+            iterations: 0,
+        }
+    }
+
+    pub fn new_plus() -> Cfr<I> {
+        Cfr {
+            plus: true,
+            ..Cfr::new()
+        }
+    }
+
+    pub fn iterations(&self) -> u64 {
+        self.iterations
+    }
+
+    /// Runs the given number of iterations, every iteration updates both players in turn
+    pub fn run<G>(&mut self, gs: &mut G, iterations: u64)
+    where
+        G: InformationSetGame<InformationSet = I>,
+    {
+        for _ in 0..iterations {
+            self.iterations += 1;
+            for player in [Player::Player1, Player::Player2] {
+                self.traverse(gs, player, 1.0, 1.0);
+            }
+        }
+    }
+
+    /// The average strategy of an information set, the strategy that converges to an
+    /// equilibrium
+    pub fn average_strategy(&self, information_set: &I) -> Option<Vec<f64>> {
+        self.strategy_sums.get(information_set).map(|sums| {
+            let total: f64 = sums.iter().sum();
+            if total > 0.0 {
+                sums.iter().map(|s| s / total).collect()
+            } else {
+                vec![1.0 / sums.len() as f64; sums.len()]
+            }
+        })
+    }
+
+    /// The average strategies of all information sets
+    pub fn average_strategies(&self) -> Strategy<I> {
+        self.strategy_sums
+            .keys()
+            .map(|i| (i.clone(), self.average_strategy(i).unwrap()))
+            .collect()
+    }
+
+    // Regret matching: proportional to the positive regrets
+    fn current_strategy(&self, information_set: &I, moves: usize) -> Vec<f64> {
+        match self.regrets.get(information_set) {
+            Some(regrets) => {
+                let total: f64 = regrets.iter().map(|r| r.max(0.0)).sum();
+                if total > 0.0 {
+                    regrets.iter().map(|r| r.max(0.0) / total).collect()
+                } else {
+                    vec![1.0 / moves as f64; moves]
+                }
+            }
+            None => vec![1.0 / moves as f64; moves],
+        }
+    }
+
+    // Returns the value for the updated player. The reach probabilities are those of the
+    // updated player and of everybody else (the opponent and chance).
+    fn traverse<G>(&mut self, gs: &mut G, updated: Player, own_reach: f64, other_reach: f64) -> f64
+    where
+        G: InformationSetGame<InformationSet = I>,
+    {
+        match gs.turn() {
+            Turn::Terminal => gs.utility(&updated),
+            Turn::Chance => {
+                let mut value = 0.0;
+                for (m, probability) in gs.chance_moves() {
+                    gs.apply_move(&m);
+                    value += probability
+                        * self.traverse(gs, updated, own_reach, other_reach * probability);
+                    gs.reverse_move(&m);
+                }
+                value
+            }
+            Turn::Player(player) => {
+                let information_set = gs.information_set();
+                let moves = gs.moves();
+                let strategy = self.current_strategy(&information_set, moves.len());
+                let mut values = Vec::with_capacity(moves.len());
+                for (m, probability) in moves.iter().zip(&strategy) {
+                    gs.apply_move(m);
+                    values.push(if player == updated {
+                        self.traverse(gs, updated, own_reach * probability, other_reach)
+                    } else {
+                        self.traverse(gs, updated, own_reach, other_reach * probability)
+                    });
+                    gs.reverse_move(m);
+                }
+                let value: f64 = values.iter().zip(&strategy).map(|(v, p)| v * p).sum();
+
+                if player == updated {
+                    let plus = self.plus;
+                    let regrets = self
+                        .regrets
+                        .entry(information_set.clone())
+                        .or_insert_with(|| vec![0.0; moves.len()]);
+                    for (regret, v) in regrets.iter_mut().zip(&values) {
+                        *regret += other_reach * (v - value);
+                        if plus {
+                            *regret = regret.max(0.0);
+                        }
+                    }
+                    // CFR+ averages with linearly increasing weights
+                    let weight = if plus { self.iterations as f64 } else { 1.0 };
+                    let sums = self
+                        .strategy_sums
+                        .entry(information_set)
+                        .or_insert_with(|| vec![0.0; moves.len()]);
+                    for (sum, p) in sums.iter_mut().zip(&strategy) {
+                        *sum += weight * own_reach * p;
+                    }
+                }
+                value
+            }
+        }
+    }
+}
+
+impl<I: Clone + Eq + Hash> Default for Cfr<I> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// The probabilities of the moves in the information set, uniform for unknown sets
+fn probabilities<I: Eq + Hash>(
+    strategy: &Strategy<I>,
+    information_set: &I,
+    moves: usize,
+) -> Vec<f64> {
+    match strategy.get(information_set) {
+        Some(probabilities) if probabilities.len() == moves => probabilities.clone(),
+        _ => vec![1.0 / moves as f64; moves],
+    }
+}
+
+/** The expected payoff of the player, if both players play the strategy */
+pub fn expected_value<G>(gs: &mut G, strategy: &Strategy<G::InformationSet>, player: &Player) -> f64
+where
+    G: InformationSetGame,
+{
+    match gs.turn() {
+        Turn::Terminal => gs.utility(player),
+        Turn::Chance => {
+            let mut value = 0.0;
+            for (m, probability) in gs.chance_moves() {
+                gs.apply_move(&m);
+                value += probability * expected_value(gs, strategy, player);
+                gs.reverse_move(&m);
+            }
+            value
+        }
+        Turn::Player(_) => {
+            let moves = gs.moves();
+            let probabilities = probabilities(strategy, &gs.information_set(), moves.len());
+            let mut value = 0.0;
+            for (m, probability) in moves.iter().zip(probabilities) {
+                gs.apply_move(m);
+                value += probability * expected_value(gs, strategy, player);
+                gs.reverse_move(m);
+            }
+            value
+        }
+    }
+}
+
+// A best response needs the states of an information set, the responder only chooses per set
+struct BestResponse<'a, G: InformationSetGame> {
+    responder: Player,
+    strategy: &'a Strategy<G::InformationSet>,
+    // States of the information sets of the responder with the reach probability of the others
+    states: HashMap<G::InformationSet, Vec<(G, f64)>>,
+    choices: HashMap<G::InformationSet, usize>,
+}
+
+impl<'a, G: InformationSetGame + Clone> BestResponse<'a, G> {
+    fn collect(&mut self, gs: &mut G, reach: f64) {
+        match gs.turn() {
+            Turn::Terminal => {}
+            Turn::Chance => {
+                for (m, probability) in gs.chance_moves() {
+                    gs.apply_move(&m);
+                    self.collect(gs, reach * probability);
+                    gs.reverse_move(&m);
+                }
+            }
+            Turn::Player(player) => {
+                let moves = gs.moves();
+                let probabilities = if player == self.responder {
+                    self.states
+                        .entry(gs.information_set())
+                        .or_default()
+                        .push((gs.clone(), reach));
+                    vec![1.0; moves.len()]
+                } else {
+                    probabilities(self.strategy, &gs.information_set(), moves.len())
+                };
+                for (m, probability) in moves.iter().zip(probabilities) {
+                    gs.apply_move(m);
+                    self.collect(gs, reach * probability);
+                    gs.reverse_move(m);
+                }
+            }
+        }
+    }
+
+    fn value(&mut self, gs: &mut G) -> f64 {
+        match gs.turn() {
+            Turn::Terminal => gs.utility(&self.responder),
+            Turn::Chance => {
+                let mut value = 0.0;
+                for (m, probability) in gs.chance_moves() {
+                    gs.apply_move(&m);
+                    value += probability * self.value(gs);
+                    gs.reverse_move(&m);
+                }
+                value
+            }
+            Turn::Player(player) if player == self.responder => {
+                let choice = self.choice(&gs.information_set());
+                let m = gs.moves()[choice].clone();
+                gs.apply_move(&m);
+                let value = self.value(gs);
+                gs.reverse_move(&m);
+                value
+            }
+            Turn::Player(_) => {
+                let moves = gs.moves();
+                let probabilities =
+                    probabilities(self.strategy, &gs.information_set(), moves.len());
+                let mut value = 0.0;
+                for (m, probability) in moves.iter().zip(probabilities) {
+                    gs.apply_move(m);
+                    value += probability * self.value(gs);
+                    gs.reverse_move(m);
+                }
+                value
+            }
+        }
+    }
+
+    // The move with the best value over all states of the information set
+    fn choice(&mut self, information_set: &G::InformationSet) -> usize {
+        if let Some(choice) = self.choices.get(information_set) {
+            return *choice;
+        }
+        let mut states = self
+            .states
+            .get(information_set)
+            .cloned()
+            .unwrap_or_default();
+        let moves = states.first().map(|(gs, _)| gs.moves().len()).unwrap_or(1);
+        let mut best = (0, f64::NEG_INFINITY);
+        for index in 0..moves {
+            let mut value = 0.0;
+            for (gs, reach) in states.iter_mut() {
+                let m = gs.moves()[index].clone();
+                gs.apply_move(&m);
+                value += *reach * self.value(gs);
+                gs.reverse_move(&m);
+            }
+            if value > best.1 {
+                best = (index, value);
+            }
+        }
+        self.choices.insert(information_set.clone(), best.0);
+        best.0
+    }
+}
+
+/** The expected payoff of a best response of the player against the strategy */
+pub fn best_response_value<G>(
+    gs: &mut G,
+    strategy: &Strategy<G::InformationSet>,
+    player: &Player,
+) -> f64
+where
+    G: InformationSetGame + Clone,
+{
+    let mut best_response = BestResponse {
+        responder: *player,
+        strategy,
+        states: HashMap::new(),
+        choices: HashMap::new(),
+    };
+    best_response.collect(gs, 1.0);
+    best_response.value(gs)
+}
+
+/** How much a best response wins on average against the strategy, compared to the value of
+ * the game. It is 0 exactly for Nash equilibria.
+ */
+pub fn exploitability<G>(gs: &mut G, strategy: &Strategy<G::InformationSet>) -> f64
+where
+    G: InformationSetGame + Clone,
+{
+    // In a zero-sum game the game values cancel out
+    (best_response_value(gs, strategy, &Player::Player1)
+        + best_response_value(gs, strategy, &Player::Player2))
+        / 2.0
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::model::Player;
+
+    // Kuhn poker: 3 cards (0 jack, 1 queen, 2 king), each player antes 1 and gets a card. Players
+    // may pass or bet 1, after a bet the other player may call (bet) or fold (pass).
+    #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+    enum KuhnMove {
+        Deal(u8, u8),
+        Pass,
+        Bet,
+    }
+
+    #[derive(Clone, PartialEq, Eq, Debug)]
+    struct KuhnPoker {
+        cards: Option<(u8, u8)>,
+        history: Vec<KuhnMove>,
+    }
+
+    impl KuhnPoker {
+        fn new() -> KuhnPoker {
+            KuhnPoker {
+                cards: None,
+                history: Vec::new(),
+            }
+        }
+
+        fn player1_utility(&self) -> Option<f64> {
+            use KuhnMove::{Bet, Pass};
+            let showdown = match self.cards {
+                Some((c1, c2)) if c1 > c2 => 1.0,
+                _ => -1.0,
+            };
+            match self.history.as_slice() {
+                [Pass, Pass] => Some(showdown),
+                [Bet, Pass] => Some(1.0),
+                [Pass, Bet, Pass] => Some(-1.0),
+                [Bet, Bet] | [Pass, Bet, Bet] => Some(2.0 * showdown),
+                _ => None,
+            }
+        }
+    }
+
+    impl InformationSetGame for KuhnPoker {
+        type Move = KuhnMove;
+        type InformationSet = (u8, Vec<KuhnMove>);
+
+        fn turn(&self) -> Turn {
+            if self.cards.is_none() {
+                Turn::Chance
+            } else if self.player1_utility().is_some() {
+                Turn::Terminal
+            } else if self.history.len() % 2 == 0 {
+                Turn::Player(Player::Player1)
+            } else {
+                Turn::Player(Player::Player2)
+            }
+        }
+
+        fn moves(&self) -> Vec<KuhnMove> {
+            vec![KuhnMove::Pass, KuhnMove::Bet]
+        }
+
+        fn chance_moves(&self) -> Vec<(KuhnMove, f64)> {
+            let mut deals = Vec::new();
+            for c1 in 0..3 {
+                for c2 in 0..3 {
+                    if c1 != c2 {
+                        deals.push((KuhnMove::Deal(c1, c2), 1.0 / 6.0));
+                    }
+                }
+            }
+            deals
+        }
+
+        fn information_set(&self) -> (u8, Vec<KuhnMove>) {
+            let (c1, c2) = self.cards.unwrap();
+            let card = if self.history.len() % 2 == 0 { c1 } else { c2 };
+            (card, self.history.clone())
+        }
+
+        fn apply_move(&mut self, m: &KuhnMove) {
+            match m {
+                KuhnMove::Deal(c1, c2) => self.cards = Some((*c1, *c2)),
+                _ => self.history.push(*m),
+            }
+        }
+
+        fn reverse_move(&mut self, m: &KuhnMove) {
+            match m {
+                KuhnMove::Deal(_, _) => self.cards = None,
+                _ => {
+                    self.history.pop();
+                }
+            }
+        }
+
+        fn utility(&self, player: &Player) -> f64 {
+            let utility = self.player1_utility().unwrap_or(0.0);
+            match player {
+                Player::Player1 => utility,
+                Player::Player2 => -utility,
+            }
+        }
+    }
+
+    const GAME_VALUE: f64 = -1.0 / 18.0;
+
+    fn solve(mut cfr: Cfr<(u8, Vec<KuhnMove>)>, iterations: u64) -> Strategy<(u8, Vec<KuhnMove>)> {
+        let mut gs = KuhnPoker::new();
+        cfr.run(&mut gs, iterations);
+        assert_eq!(gs, KuhnPoker::new());
+        cfr.average_strategies()
+    }
+
+    #[test]
+    fn uniform_strategy_is_exploitable() {
+        // Setup
+        let mut gs = KuhnPoker::new();
+        let uniform = Strategy::new();
+
+        // Act
+        let value = expected_value(&mut gs, &uniform, &Player::Player1);
+        let exploitability = exploitability(&mut gs, &uniform);
+
+        // Test
+        // Against a random opponent the first player wins on average 1/8
+        assert!((value - 0.125).abs() < 1e-9);
+        assert!(exploitability > 0.3);
+    }
+
+    #[test]
+    fn cfr_approaches_the_equilibrium() {
+        // Act
+        let strategy = solve(Cfr::new(), 2000);
+
+        // Test
+        let mut gs = KuhnPoker::new();
+        assert!((expected_value(&mut gs, &strategy, &Player::Player1) - GAME_VALUE).abs() < 0.01);
+        assert!(exploitability(&mut gs, &strategy) < 0.01);
+        // 12 information sets: 3 cards for 4 histories (P1 first, P2 after pass or bet, P1
+        // after pass and bet)
+        assert_eq!(strategy.len(), 12);
+    }
+
+    #[test]
+    fn cfr_plus_approaches_the_equilibrium() {
+        // Act
+        let strategy = solve(Cfr::new_plus(), 1000);
+
+        // Test
+        let mut gs = KuhnPoker::new();
+        assert!((expected_value(&mut gs, &strategy, &Player::Player1) - GAME_VALUE).abs() < 0.001);
+        let exploitability_plus = exploitability(&mut gs, &strategy);
+        assert!(exploitability_plus < 0.005);
+        let strategy_cfr = solve(Cfr::new(), 1000);
+        assert!(exploitability_plus < exploitability(&mut gs, &strategy_cfr));
+
+        // Known properties of the equilibria: the first player bets a jack with a probability
+        // alpha up to 1/3 and a king with 3 * alpha, the second player always calls with a king
+        // and calls with a queen with a probability of 1/3
+        let bet = |card: u8, history: Vec<KuhnMove>| strategy[&(card, history)][1];
+        let alpha = bet(0, vec![]);
+        assert!(alpha < 1.0 / 3.0 + 0.01);
+        assert!((bet(2, vec![]) - 3.0 * alpha).abs() < 0.05);
+        assert!(bet(2, vec![KuhnMove::Bet]) > 0.99);
+        assert!((bet(1, vec![KuhnMove::Bet]) - 1.0 / 3.0).abs() < 0.05);
+        assert!(bet(0, vec![KuhnMove::Bet]) < 0.01);
+    }
+}
@@ -0,0 +1,481 @@
+/*! AlphaZero-style training (https://arxiv.org/abs/1712.01815), small enough for a CPU.
+ *
+ * [puct_search] is a Monte Carlo tree search, guided by the policy and value of a
+ * [PolicyValueModel] instead of random playouts. [self_play] plays a game with it against
+ * itself and records the visit distribution of every search together with the outcome. The
+ * model is then trained on these records with [PolicyValueModel::train_policy], and
+ * [compare_models] lets the new model play against the old one with the tournament runner.
+ */
+use crate::learning::policy_value::{PolicySample, PolicyValueModel};
+use crate::learning::{FeatureExtractor, LearnedEvaluation, ValueModel};
+use crate::model::{GameState, MoveIterator, MoveOutcome, Player};
+use crate::tools::random::Random;
+use crate::tournament::{ki_battle_with_options, MatchOptions};
+
+/** Features for a [PolicyValueModel]: every move of the game has a fixed index into the policy */
+pub trait PolicyFeatures<GS: GameState>: FeatureExtractor<GS> {
+    /** Number of moves, the length of the policy */
+    fn policy_size(&self) -> usize;
+
+    /** The index of a move in the policy, below [PolicyFeatures::policy_size] */
+    fn move_index(&self, gs: &GS, m: &GS::Move) -> usize;
+}
+
+/// Settings for [puct_search] and [self_play]
+#[derive(Clone, Debug)]
+pub struct SelfPlayOptions {
+    /// Number of simulations per search
+    pub simulations: usize,
+    /// Weight of the prior compared to the values, c_puct in the paper
+    pub exploration: f64,
+    /// Number of plies at the start of the game, where moves are chosen proportional to the
+    /// visits. Later the most visited move is played.
+    pub sampling_plies: usize,
+    /// Games are stopped as tie after this number of plies
+    pub max_plies: usize,
+}
+
+impl SelfPlayOptions {
+    pub fn new(simulations: usize) -> SelfPlayOptions {
+        SelfPlayOptions {
+            simulations,
+            exploration: 1.5,
+            sampling_plies: 4,
+            max_plies: 1000,
+        }
+    }
+}
+
+struct Node<M> {
+    link: Option<(usize, M)>,
+    children: Vec<usize>,
+    expanded: bool,
+    prior: f64,
+    visits: u32,
+    // Sum of the values for the player who made the move to this node
+    value_sum: f64,
+    // The value for the player who made the move to this node, if the move ended the game
+    terminal: Option<f64>,
+    // The player to move changed with the move to this node
+    switched: bool,
+}
+
+impl<M> Node<M> {
+    fn new(link: Option<(usize, M)>, prior: f64, terminal: Option<f64>, switched: bool) -> Node<M> {
+        Node {
+            link,
+            children: Vec::new(),
+            expanded: false,
+            prior,
+            visits: 0,
+            value_sum: 0.0,
+            terminal,
+            switched,
+        }
+    }
+}
+
+/** Runs a PUCT search from the game state and returns the moves with the number of visits of
+ * each. The game state is unchanged afterwards.
+ */
+pub fn puct_search<GS, FE, M>(
+    gs: &mut GS,
+    extractor: &FE,
+    model: &M,
+    options: &SelfPlayOptions,
+) -> Vec<(GS::Move, u32)>
+where
+    GS: GameState,
+    FE: PolicyFeatures<GS>,
+    M: PolicyValueModel,
+{
+    let mut tree: Vec<Node<GS::Move>> = vec![Node::new(None, 1.0, None, false)];
+    for _ in 0..options.simulations.max(1) {
+        // Selection
+        let mut node = 0;
+        let mut path = Vec::new();
+        while tree[node].expanded && !tree[node].children.is_empty() {
+            let parent_visits = f64::from(tree[node].visits);
+            let child = *tree[node]
+                .children
+                .iter()
+                .max_by(|a, b| {
+                    let score_a = puct_score(&tree[**a], parent_visits, options.exploration);
+                    let score_b = puct_score(&tree[**b], parent_visits, options.exploration);
+                    score_a.total_cmp(&score_b)
+                })
+                .unwrap();
+            let m = tree[child].link.as_ref().map(|(_, m)| m.clone()).unwrap();
+            gs.apply_move(&m);
+            path.push(m);
+            node = child;
+        }
+
+        // Expansion and evaluation, the value is for the player to move in the node
+        let value = match tree[node].terminal {
+            Some(value) => {
+                if tree[node].switched {
+                    -value
+                } else {
+                    value
+                }
+            }
+            None => expand(gs, &mut tree, node, extractor, model),
+        };
+
+        for m in path.iter().rev() {
+            gs.reverse_move(m);
+        }
+
+        // Backup
+        let mut current = node;
+        let mut value = value;
+        loop {
+            // Value for the player who made the move to the current node
+            if tree[current].switched {
+                value = -value;
+            }
+            tree[current].visits += 1;
+            tree[current].value_sum += value;
+            match tree[current].link {
+                Some((parent, _)) => current = parent,
+                None => break,
+            }
+        }
+    }
+
+    tree[0]
+        .children
+        .iter()
+        .map(|c| {
+            let m = tree[*c].link.as_ref().map(|(_, m)| m.clone()).unwrap();
+            (m, tree[*c].visits)
+        })
+        .collect()
+}
+
+fn puct_score<M>(node: &Node<M>, parent_visits: f64, exploration: f64) -> f64 {
+    let q = if node.visits == 0 {
+        0.0
+    } else {
+        node.value_sum / f64::from(node.visits)
+    };
+    q + exploration * node.prior * parent_visits.sqrt() / (1.0 + f64::from(node.visits))
+}
+
+// Adds the children of the node with the priors of the model, returns the value of the model
+fn expand<GS, FE, M>(
+    gs: &mut GS,
+    tree: &mut Vec<Node<GS::Move>>,
+    node: usize,
+    extractor: &FE,
+    model: &M,
+) -> f64
+where
+    GS: GameState,
+    FE: PolicyFeatures<GS>,
+    M: PolicyValueModel,
+{
+    let player = gs.active_player();
+    let (policy, value) = model.predict_policy_value(&extractor.features(gs, &player));
+    let mut children = Vec::new();
+    let mut move_iterator = gs.move_iterator();
+    while let Some(m) = move_iterator.next(gs) {
+        let prior = policy[extractor.move_index(gs, m)];
+        let outcome = gs.apply_move(m);
+        let switched = gs.active_player() != player;
+        gs.reverse_move(m);
+        let terminal = match outcome {
+            MoveOutcome::PlayerWon(p) if p == player => Some(1.0),
+            MoveOutcome::PlayerWon(_) => Some(-1.0),
+            MoveOutcome::Tie => Some(0.0),
+            MoveOutcome::SwitchPlayer(_) | MoveOutcome::ContinuePlayer(_) => None,
+        };
+        children.push(Node::new(
+            Some((node, m.clone())),
+            prior,
+            terminal,
+            switched,
+        ));
+    }
+    // The priors of the legal moves add up to 1
+    let total: f64 = children.iter().map(|c| c.prior).sum();
+    let count = children.len() as f64;
+    for mut child in children {
+        child.prior = if total > 0.0 {
+            child.prior / total
+        } else {
+            1.0 / count
+        };
+        tree.push(child);
+        let index = tree.len() - 1;
+        tree[node].children.push(index);
+    }
+    tree[node].expanded = true;
+    value
+}
+
+/** A game played by [self_play] */
+pub struct SelfPlayGame<M> {
+    pub moves: Vec<M>,
+    pub winner: Option<Player>,
+    /// One sample per position, the policy is the visit distribution of the search
+    pub samples: Vec<PolicySample>,
+}
+
+/** Plays a game of the model against itself, every move chosen by a [puct_search]. The game
+ * state is returned to the initial state.
+ */
+pub fn self_play<GS, FE, M>(
+    gs: &mut GS,
+    extractor: &FE,
+    model: &M,
+    options: &SelfPlayOptions,
+    random: &mut Random,
+) -> SelfPlayGame<GS::Move>
+where
+    GS: GameState,
+    FE: PolicyFeatures<GS>,
+    M: PolicyValueModel,
+{
+    let mut moves = Vec::new();
+    // Samples with the player to move
+    let mut samples = Vec::new();
+    let mut winner = None;
+
+    while moves.len() < options.max_plies {
+        let visits = puct_search(gs, extractor, model, options);
+        if visits.is_empty() {
+            break;
+        }
+        let player = gs.active_player();
+        let total: u32 = visits.iter().map(|(_, v)| v).sum();
+        let mut policy = vec![0.0; extractor.policy_size()];
+        for (m, v) in &visits {
+            policy[extractor.move_index(gs, m)] += f64::from(*v) / f64::from(total.max(1));
+        }
+        samples.push((
+            player,
+            PolicySample {
+                features: extractor.features(gs, &player),
+                policy,
+                value: 0.0,
+            },
+        ));
+
+        let index = if moves.len() < options.sampling_plies {
+            let weights: Vec<u64> = visits.iter().map(|(_, v)| u64::from(*v)).collect();
+            random.weighted_index(&weights).unwrap_or(0)
+        } else {
+            // The first of the most visited moves
+            (0..visits.len())
+                .rev()
+                .max_by_key(|i| visits[*i].1)
+                .unwrap()
+        };
+        let m = visits[index].0.clone();
+        let outcome = gs.apply_move(&m);
+        moves.push(m);
+        match outcome {
+            MoveOutcome::PlayerWon(p) => {
+                winner = Some(p);
+                break;
+            }
+            MoveOutcome::Tie => break,
+            MoveOutcome::SwitchPlayer(_) | MoveOutcome::ContinuePlayer(_) => {}
+        }
+    }
+
+    for m in moves.iter().rev() {
+        gs.reverse_move(m);
+    }
+
+    let samples = samples
+        .into_iter()
+        .map(|(player, mut sample)| {
+            sample.value = match winner {
+                Some(p) if p == player => 1.0,
+                Some(_) => -1.0,
+                None => 0.0,
+            };
+            sample
+        })
+        .collect();
+    SelfPlayGame {
+        moves,
+        winner,
+        samples,
+    }
+}
+
+/** Result of [compare_models], counted for the new model */
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Comparison {
+    pub wins: usize,
+    pub losses: usize,
+    pub ties: usize,
+}
+
+/** Plays `games` games between two models, using their value heads as evaluation for a
+ * negamax search of the given depth in [ki_battle_with_options]. The models alternate who
+ * starts, and the first `random_opening_plies` of every game are random, so the games differ.
+ */
+pub fn compare_models<GS, FE, M, F>(
+    new_game: F,
+    extractor: &FE,
+    new_model: &M,
+    old_model: &M,
+    games: usize,
+    depth: i8,
+    random_opening_plies: usize,
+) -> Comparison
+where
+    GS: GameState,
+    FE: FeatureExtractor<GS>,
+    M: ValueModel,
+    F: Fn() -> GS,
+{
+    let mut comparison = Comparison::default();
+    for game in 0..games {
+        let new_starts = game % 2 == 0;
+        let (first, second) = if new_starts {
+            (new_model, old_model)
+        } else {
+            (old_model, new_model)
+        };
+        let mut gs1 = LearnedEvaluation::new(new_game(), extractor, first);
+        let mut gs2 = LearnedEvaluation::new(new_game(), extractor, second);
+        let mut options = MatchOptions::new(depth, depth);
+        options.seed = game as u64;
+        options.random_opening_plies = random_opening_plies;
+        let result = ki_battle_with_options(
+            &mut gs1,
+            &mut gs2,
+            |m, _| Some(m.clone()),
+            |m, _| Some(m.clone()),
+            &options,
+        );
+        let new_player = if new_starts {
+            Player::Player1
+        } else {
+            Player::Player2
+        };
+        match result.winner {
+            Some(p) if p == new_player => comparison.wins += 1,
+            Some(_) => comparison.losses += 1,
+            None => comparison.ties += 1,
+        }
+    }
+    comparison
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::learning::policy_value::PolicyValueMlp;
+    use crate::learning::test::NimFeatures;
+    use crate::testing::test_games::NimGameState;
+
+    impl PolicyFeatures<NimGameState> for NimFeatures {
+        fn policy_size(&self) -> usize {
+            2
+        }
+
+        fn move_index(&self, _gs: &NimGameState, m: &u32) -> usize {
+            *m as usize - 1
+        }
+    }
+
+    #[test]
+    fn search_finds_win() {
+        // Setup
+        let model = PolicyValueMlp::new(3, 4, 2, &mut Random::new(1));
+        let mut gs = NimGameState::new(5);
+
+        // Act
+        let visits = puct_search(&mut gs, &NimFeatures, &model, &SelfPlayOptions::new(200));
+
+        // Test
+        // Taking 2 leaves 3 stones, a lost position for the opponent
+        assert_eq!(gs, NimGameState::new(5));
+        assert_eq!(visits.len(), 2);
+        assert_eq!(visits.iter().map(|(_, v)| v).sum::<u32>(), 199);
+        let best = visits.iter().max_by_key(|(_, v)| *v).unwrap();
+        assert_eq!(best.0, 2);
+    }
+
+    #[test]
+    fn self_play_records_game() {
+        // Setup
+        let mut random = Random::new(2);
+        let model = PolicyValueMlp::new(3, 4, 2, &mut random);
+        let mut gs = NimGameState::new(6);
+
+        // Act
+        let game = self_play(
+            &mut gs,
+            &NimFeatures,
+            &model,
+            &SelfPlayOptions::new(20),
+            &mut random,
+        );
+
+        // Test
+        assert_eq!(gs, NimGameState::new(6));
+        assert_eq!(game.moves.iter().sum::<u32>(), 6);
+        assert_eq!(game.samples.len(), game.moves.len());
+        let winner = game.winner.unwrap();
+        // Player1 moves first, the winner made the last move
+        assert_eq!(game.samples.last().unwrap().value, 1.0);
+        assert_eq!(
+            game.samples[0].value,
+            if winner == Player::Player1 { 1.0 } else { -1.0 }
+        );
+        for sample in &game.samples {
+            assert!((sample.policy.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn training_loop_improves_model() {
+        // Setup
+        let mut random = Random::new(3);
+        let initial = PolicyValueMlp::new(3, 8, 2, &mut random);
+        let mut model = initial.clone();
+        let options = SelfPlayOptions::new(30);
+
+        // Act
+        for _ in 0..3 {
+            let mut samples = Vec::new();
+            for stones in 4..20 {
+                let mut gs = NimGameState::new(stones);
+                samples.extend(
+                    self_play(&mut gs, &NimFeatures, &model, &options, &mut random).samples,
+                );
+            }
+            model.train_policy(&samples, 20, 0.05, &mut random);
+        }
+        let comparison = compare_models(
+            || NimGameState::new(10),
+            &NimFeatures,
+            &model,
+            &initial,
+            4,
+            1,
+            0,
+        );
+
+        // Test
+        // With 4 stones taking 1 leaves a lost position for the opponent
+        let (policy, _) = model
+            .predict_policy_value(&NimFeatures.features(&NimGameState::new(4), &Player::Player1));
+        assert!(policy[0] > policy[1]);
+        let (_, lost) = model
+            .predict_policy_value(&NimFeatures.features(&NimGameState::new(6), &Player::Player1));
+        let (_, won) = model
+            .predict_policy_value(&NimFeatures.features(&NimGameState::new(7), &Player::Player1));
+        assert!(lost < won);
+        assert_eq!(comparison.wins + comparison.losses + comparison.ties, 4);
+        assert!(comparison.wins >= comparison.losses);
+    }
+}
@@ -0,0 +1,67 @@
+//! A linear model with a tanh output.
+use crate::learning::{Sample, ValueModel};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LinearModel {
+    pub weights: Vec<f64>,
+    pub bias: f64,
+}
+
+impl LinearModel {
+    /// A model predicting 0 for everything
+    pub fn new(inputs: usize) -> LinearModel {
+        LinearModel {
+            weights: vec![0.0; inputs],
+            bias: 0.0,
+        }
+    }
+
+    fn weighted_sum(&self, features: &[f64]) -> f64 {
+        self.bias
+            + self
+                .weights
+                .iter()
+                .zip(features)
+                .map(|(w, f)| w * f)
+                .sum::<f64>()
+    }
+}
+
+impl ValueModel for LinearModel {
+    fn predict(&self, features: &[f64]) -> f64 {
+        self.weighted_sum(features).tanh()
+    }
+
+    fn train_sample(&mut self, sample: &Sample, learning_rate: f64) {
+        let prediction = self.predict(&sample.features);
+        // Derivative of the squared error through tanh
+        let gradient = 2.0 * (prediction - sample.target) * (1.0 - prediction * prediction);
+        for (w, f) in self.weights.iter_mut().zip(&sample.features) {
+            *w -= learning_rate * gradient * f;
+        }
+        self.bias -= learning_rate * gradient;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn learns_a_single_sample() {
+        // Setup
+        let mut model = LinearModel::new(2);
+        let sample = Sample {
+            features: vec![1.0, 0.5],
+            target: 0.5,
+        };
+
+        // Act
+        for _ in 0..1000 {
+            model.train_sample(&sample, 0.1);
+        }
+
+        // Test
+        assert!((model.predict(&sample.features) - 0.5).abs() < 0.01);
+    }
+}
@@ -0,0 +1,104 @@
+//! A multilayer perceptron with one hidden layer, tanh activations and a tanh output.
+use crate::learning::{Sample, ValueModel};
+use crate::tools::random::Random;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MlpModel {
+    /// One row of input weights per hidden neuron
+    pub hidden_weights: Vec<Vec<f64>>,
+    pub hidden_bias: Vec<f64>,
+    pub output_weights: Vec<f64>,
+    pub output_bias: f64,
+}
+
+impl MlpModel {
+    /// A model with small random weights
+    pub fn new(inputs: usize, hidden: usize, random: &mut Random) -> MlpModel {
+        // Xavier initialization
+        let mut init = |n: usize| (random.next_f64() * 2.0 - 1.0) / (n as f64).sqrt();
+        MlpModel {
+            hidden_weights: (0..hidden)
+                .map(|_| (0..inputs).map(|_| init(inputs)).collect())
+                .collect(),
+            hidden_bias: vec![0.0; hidden],
+            output_weights: (0..hidden).map(|_| init(hidden)).collect(),
+            output_bias: 0.0,
+        }
+    }
+
+    fn hidden(&self, features: &[f64]) -> Vec<f64> {
+        self.hidden_weights
+            .iter()
+            .zip(&self.hidden_bias)
+            .map(|(weights, bias)| {
+                let sum: f64 = weights.iter().zip(features).map(|(w, f)| w * f).sum();
+                (sum + bias).tanh()
+            })
+            .collect()
+    }
+
+    fn output(&self, hidden: &[f64]) -> f64 {
+        let sum: f64 = self
+            .output_weights
+            .iter()
+            .zip(hidden)
+            .map(|(w, h)| w * h)
+            .sum();
+        (sum + self.output_bias).tanh()
+    }
+}
+
+impl ValueModel for MlpModel {
+    fn predict(&self, features: &[f64]) -> f64 {
+        self.output(&self.hidden(features))
+    }
+
+    fn train_sample(&mut self, sample: &Sample, learning_rate: f64) {
+        // Backpropagation of the squared error
+        let hidden = self.hidden(&sample.features);
+        let prediction = self.output(&hidden);
+        let output_gradient = 2.0 * (prediction - sample.target) * (1.0 - prediction * prediction);
+        for (i, h) in hidden.iter().enumerate() {
+            let hidden_gradient = output_gradient * self.output_weights[i] * (1.0 - h * h);
+            self.output_weights[i] -= learning_rate * output_gradient * h;
+            for (w, f) in self.hidden_weights[i].iter_mut().zip(&sample.features) {
+                *w -= learning_rate * hidden_gradient * f;
+            }
+            self.hidden_bias[i] -= learning_rate * hidden_gradient;
+        }
+        self.output_bias -= learning_rate * output_gradient;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn learns_xor() {
+        // Setup
+        // Not possible for a linear model
+        let samples: Vec<Sample> = [
+            (0.0, 0.0, -0.9),
+            (0.0, 1.0, 0.9),
+            (1.0, 0.0, 0.9),
+            (1.0, 1.0, -0.9),
+        ]
+        .iter()
+        .map(|(a, b, target)| Sample {
+            features: vec![*a, *b],
+            target: *target,
+        })
+        .collect();
+        let mut random = Random::new(3);
+        let mut model = MlpModel::new(2, 4, &mut random);
+
+        // Act
+        model.train(&samples, 5000, 0.05, &mut random);
+
+        // Test
+        for sample in &samples {
+            assert!((model.predict(&sample.features) - sample.target).abs() < 0.2);
+        }
+    }
+}
@@ -0,0 +1,352 @@
+/*! Evaluations learned from self-play, as replacement for handcrafted heuristics.
+ *
+ * A [FeatureExtractor] turns a game state into a numeric feature vector. A [ValueModel]
+ * ([linear::LinearModel] or the small neural network [mlp::MlpModel]) predicts the outcome of
+ * the game from the features and is trained on CPU from recorded games in a [TrainingSet].
+ * [LearnedEvaluation] wraps a game state, so that [crate::negamax] uses the model as
+ * evaluation.
+ */
+use crate::model::{GameState, MiniMaxGameState, MoveIterator, MoveOutcome, Player};
+use crate::tools::random::Random;
+use crate::tournament::{replay_game, MatchResult};
+use std::marker::PhantomData;
+
+pub mod alpha_zero;
+pub mod linear;
+pub mod mlp;
+pub mod policy_value;
+
+/** The factor between a model prediction in (-1, 1) and the evaluation of [LearnedEvaluation] */
+pub const EVALUATION_SCALE: f64 = 1_000_000.0;
+
+/** Describes a game state with numbers, the input of a [ValueModel] */
+pub trait FeatureExtractor<GS: GameState> {
+    /** Length of the feature vectors */
+    fn feature_count(&self) -> usize;
+
+    /** The features of the game state, from the view of the given player */
+    fn features(&self, gs: &GS, player: &Player) -> Vec<f64>;
+}
+
+/** A feature vector with the expected game outcome: 1 win, 0 tie, -1 loss */
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sample {
+    pub features: Vec<f64>,
+    pub target: f64,
+}
+
+/** A trainable function from features to the expected game outcome */
+pub trait ValueModel {
+    /** The predicted outcome, between -1 (loss) and 1 (win) */
+    fn predict(&self, features: &[f64]) -> f64;
+
+    /** One gradient descent step on the squared error of a single sample */
+    fn train_sample(&mut self, sample: &Sample, learning_rate: f64);
+
+    /** Stochastic gradient descent over the samples, in a random order for every epoch */
+    fn train(
+        &mut self,
+        samples: &[Sample],
+        epochs: usize,
+        learning_rate: f64,
+        random: &mut Random,
+    ) {
+        let mut order: Vec<usize> = (0..samples.len()).collect();
+        for _ in 0..epochs {
+            shuffle(&mut order, random);
+            for index in &order {
+                self.train_sample(&samples[*index], learning_rate);
+            }
+        }
+    }
+
+    /** The mean squared error on the samples */
+    fn error(&self, samples: &[Sample]) -> f64 {
+        if samples.is_empty() {
+            return 0.0;
+        }
+        let total: f64 = samples
+            .iter()
+            .map(|s| (self.predict(&s.features) - s.target).powi(2))
+            .sum();
+        total / samples.len() as f64
+    }
+}
+
+// Fisher-Yates
+pub(crate) fn shuffle<T>(values: &mut [T], random: &mut Random) {
+    for i in (1..values.len()).rev() {
+        let j = random.below(i as u64 + 1) as usize;
+        values.swap(i, j);
+    }
+}
+
+/** Samples from recorded games, every position labeled with the outcome for its active player */
+pub struct TrainingSet {
+    /// Plies at the start of each game, that are not used
+    pub skip_plies: usize,
+    pub samples: Vec<Sample>,
+}
+
+impl TrainingSet {
+    pub fn new() -> TrainingSet {
+        TrainingSet {
+            skip_plies: 0,
+            samples: Vec::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// Adds the positions of a game, given by its initial state, its moves and the winner
+    /// (`None` for a tie). The game state is returned to the initial state.
+    pub fn add_game<GS, FE>(
+        &mut self,
+        extractor: &FE,
+        initial: &mut GS,
+        moves: &[GS::Move],
+        winner: Option<Player>,
+    ) where
+        GS: GameState,
+        FE: FeatureExtractor<GS>,
+    {
+        replay_game(initial, moves, self.skip_plies, |gs| {
+            let player = gs.active_player();
+            let target = match winner {
+                Some(p) if p == player => 1.0,
+                Some(_) => -1.0,
+                None => 0.0,
+            };
+            self.samples.push(Sample {
+                features: extractor.features(gs, &player),
+                target,
+            });
+        });
+    }
+
+    /// Adds the positions of a game played by [crate::tournament::ki_battle_with_options]
+    pub fn add_match_result<GS, FE>(
+        &mut self,
+        extractor: &FE,
+        initial: &mut GS,
+        result: &MatchResult<GS::Move>,
+    ) where
+        GS: GameState,
+        FE: FeatureExtractor<GS>,
+    {
+        self.add_game(extractor, initial, &result.moves, result.winner);
+    }
+}
+
+impl Default for TrainingSet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/** A game state, that is evaluated by a [ValueModel]. Moves are the moves of the wrapped
+ * state, so it can be searched with [crate::negamax] and played in tournaments.
+ */
+pub struct LearnedEvaluation<'a, GS, FE, M> {
+    pub state: GS,
+    pub extractor: &'a FE,
+    pub model: &'a M,
+}
+
+impl<'a, GS, FE, M> LearnedEvaluation<'a, GS, FE, M> {
+    pub fn new(state: GS, extractor: &'a FE, model: &'a M) -> LearnedEvaluation<'a, GS, FE, M> {
+        LearnedEvaluation {
+            state,
+            extractor,
+            model,
+        }
+    }
+}
+
+pub struct LearnedMoveIterator<'a, GS: GameState, FE, M> {
+    inner: GS::MoveIterator,
+    phantom: PhantomData<LearnedEvaluation<'a, GS, FE, M>>,
+}
+
+impl<'a, GS: GameState, FE, M> MoveIterator for LearnedMoveIterator<'a, GS, FE, M> {
+    type Move = GS::Move;
+    type GameState = LearnedEvaluation<'a, GS, FE, M>;
+
+    fn next(&mut self, gs: &Self::GameState) -> Option<&Self::Move> {
+        self.inner.next(&gs.state)
+    }
+}
+
+impl<'a, GS: GameState, FE, M> GameState for LearnedEvaluation<'a, GS, FE, M> {
+    type Move = GS::Move;
+    type MoveIterator = LearnedMoveIterator<'a, GS, FE, M>;
+
+    fn active_player(&self) -> Player {
+        self.state.active_player()
+    }
+
+    fn move_iterator(&self) -> Self::MoveIterator {
+        LearnedMoveIterator {
+            inner: self.state.move_iterator(),
+            phantom: PhantomData,
+        }
+    }
+
+    fn apply_move(&mut self, m: &Self::Move) -> MoveOutcome {
+        self.state.apply_move(m)
+    }
+
+    fn reverse_move(&mut self, m: &Self::Move) {
+        self.state.reverse_move(m)
+    }
+}
+
+impl<'a, GS, FE, M> MiniMaxGameState for LearnedEvaluation<'a, GS, FE, M>
+where
+    GS: GameState,
+    FE: FeatureExtractor<GS>,
+    M: ValueModel,
+{
+    fn evaluate(&self, player: &Player) -> i64 {
+        let features = self.extractor.features(&self.state, player);
+        (self.model.predict(&features) * EVALUATION_SCALE) as i64
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::learning::linear::LinearModel;
+    use crate::learning::mlp::MlpModel;
+    use crate::negamax::negamax;
+    use crate::testing::test_games::NimGameState;
+
+    // The remaining stones modulo 3, one hot encoded, negative if the player is not to move
+    pub(super) struct NimFeatures;
+
+    impl FeatureExtractor<NimGameState> for NimFeatures {
+        fn feature_count(&self) -> usize {
+            3
+        }
+
+        fn features(&self, gs: &NimGameState, player: &Player) -> Vec<f64> {
+            let mut features = vec![0.0; 3];
+            features[(gs.stones % 3) as usize] = if *player == gs.active_player {
+                1.0
+            } else {
+                -1.0
+            };
+            features
+        }
+    }
+
+    // Games with random moves
+    fn random_games(count: usize, random: &mut Random) -> TrainingSet {
+        let mut training_set = TrainingSet::new();
+        for _ in 0..count {
+            let stones = 3 + random.below(15) as u32;
+            let mut gs = NimGameState::new(stones);
+            let mut moves = Vec::new();
+            let winner = loop {
+                let m = if gs.stones == 1 {
+                    1
+                } else {
+                    1 + random.below(2) as u32
+                };
+                moves.push(m);
+                if let MoveOutcome::PlayerWon(p) = gs.apply_move(&m) {
+                    break p;
+                }
+            };
+            training_set.add_game(
+                &NimFeatures,
+                &mut NimGameState::new(stones),
+                &moves,
+                Some(winner),
+            );
+        }
+        training_set
+    }
+
+    #[test]
+    fn add_game() {
+        // Setup
+        let mut training_set = TrainingSet::new();
+        let mut gs = NimGameState::new(4);
+
+        // Act
+        // 4 -> 3 -> 1 -> 0, Player2 wins
+        training_set.add_game(&NimFeatures, &mut gs, &[1, 2, 1], Some(Player::Player2));
+
+        // Test
+        assert_eq!(gs, NimGameState::new(4));
+        assert_eq!(training_set.len(), 3);
+        assert_eq!(training_set.samples[0].target, -1.0);
+        assert_eq!(training_set.samples[0].features, vec![0.0, 1.0, 0.0]);
+        assert_eq!(training_set.samples[1].target, 1.0);
+    }
+
+    #[test]
+    fn shuffle_keeps_values() {
+        // Setup
+        let mut values: Vec<u32> = (0..20).collect();
+
+        // Act
+        shuffle(&mut values, &mut Random::new(1));
+
+        // Test
+        assert_ne!(values, (0..20).collect::<Vec<u32>>());
+        values.sort();
+        assert_eq!(values, (0..20).collect::<Vec<u32>>());
+    }
+
+    fn check_learned_play<M: ValueModel>(model: &M) {
+        // A position with stones divisible by 3 is lost for the active player
+        let lost = LearnedEvaluation::new(NimGameState::new(6), &NimFeatures, model);
+        let won = LearnedEvaluation::new(NimGameState::new(7), &NimFeatures, model);
+        assert!(lost.evaluate(&Player::Player1) < won.evaluate(&Player::Player1));
+        assert!(lost.evaluate(&Player::Player2) > won.evaluate(&Player::Player2));
+
+        // Taking 1 stone leaves the opponent with 6 stones
+        let mut gs = LearnedEvaluation::new(NimGameState::new(7), &NimFeatures, model);
+        assert_eq!(negamax(&mut gs, 1).1, Some(1));
+    }
+
+    #[test]
+    fn linear_model_learns_nim() {
+        // Setup
+        let mut random = Random::new(7);
+        let training_set = random_games(200, &mut random);
+        let mut model = LinearModel::new(NimFeatures.feature_count());
+        let start_error = model.error(&training_set.samples);
+
+        // Act
+        model.train(&training_set.samples, 20, 0.01, &mut random);
+
+        // Test
+        assert!(model.error(&training_set.samples) < start_error);
+        check_learned_play(&model);
+    }
+
+    #[test]
+    fn mlp_model_learns_nim() {
+        // Setup
+        let mut random = Random::new(7);
+        let training_set = random_games(200, &mut random);
+        let mut model = MlpModel::new(NimFeatures.feature_count(), 8, &mut random);
+        let start_error = model.error(&training_set.samples);
+
+        // Act
+        model.train(&training_set.samples, 20, 0.01, &mut random);
+
+        // Test
+        assert!(model.error(&training_set.samples) < start_error);
+        check_learned_play(&model);
+    }
+}
@@ -0,0 +1,219 @@
+//! A network with a policy and a value head, as used by [crate::learning::alpha_zero].
+use crate::learning::{shuffle, Sample, ValueModel};
+use crate::tools::random::Random;
+
+/** Features with the targets for both heads: the move probabilities (indexed like
+ * [crate::learning::alpha_zero::PolicyFeatures::move_index]) and the outcome for the active
+ * player (1 win, 0 tie, -1 loss).
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct PolicySample {
+    pub features: Vec<f64>,
+    pub policy: Vec<f64>,
+    pub value: f64,
+}
+
+/** A trainable function from features to move probabilities and the expected outcome */
+pub trait PolicyValueModel {
+    /** The probabilities of all moves and the predicted outcome between -1 and 1 */
+    fn predict_policy_value(&self, features: &[f64]) -> (Vec<f64>, f64);
+
+    /** One gradient descent step on the cross entropy of the policy plus the squared error of
+     * the value
+     */
+    fn train_policy_sample(&mut self, sample: &PolicySample, learning_rate: f64);
+
+    /** Stochastic gradient descent over the samples, in a random order for every epoch */
+    fn train_policy(
+        &mut self,
+        samples: &[PolicySample],
+        epochs: usize,
+        learning_rate: f64,
+        random: &mut Random,
+    ) {
+        let mut order: Vec<usize> = (0..samples.len()).collect();
+        for _ in 0..epochs {
+            shuffle(&mut order, random);
+            for index in &order {
+                self.train_policy_sample(&samples[*index], learning_rate);
+            }
+        }
+    }
+}
+
+/** A multilayer perceptron with one shared tanh hidden layer, a softmax policy head and a tanh
+ * value head. As [ValueModel] only the value head is used and trained.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct PolicyValueMlp {
+    /// One row of input weights per hidden neuron
+    pub hidden_weights: Vec<Vec<f64>>,
+    pub hidden_bias: Vec<f64>,
+    /// One row of hidden weights per move
+    pub policy_weights: Vec<Vec<f64>>,
+    pub policy_bias: Vec<f64>,
+    pub value_weights: Vec<f64>,
+    pub value_bias: f64,
+}
+
+impl PolicyValueMlp {
+    /// A model with small random weights
+    pub fn new(inputs: usize, hidden: usize, moves: usize, random: &mut Random) -> PolicyValueMlp {
+        // Xavier initialization
+        let mut init = |n: usize| (random.next_f64() * 2.0 - 1.0) / (n as f64).sqrt();
+        PolicyValueMlp {
+            hidden_weights: (0..hidden)
+                .map(|_| (0..inputs).map(|_| init(inputs)).collect())
+                .collect(),
+            hidden_bias: vec![0.0; hidden],
+            policy_weights: (0..moves)
+                .map(|_| (0..hidden).map(|_| init(hidden)).collect())
+                .collect(),
+            policy_bias: vec![0.0; moves],
+            value_weights: (0..hidden).map(|_| init(hidden)).collect(),
+            value_bias: 0.0,
+        }
+    }
+
+    fn hidden(&self, features: &[f64]) -> Vec<f64> {
+        self.hidden_weights
+            .iter()
+            .zip(&self.hidden_bias)
+            .map(|(weights, bias)| {
+                let sum: f64 = weights.iter().zip(features).map(|(w, f)| w * f).sum();
+                (sum + bias).tanh()
+            })
+            .collect()
+    }
+
+    fn policy(&self, hidden: &[f64]) -> Vec<f64> {
+        let logits: Vec<f64> = self
+            .policy_weights
+            .iter()
+            .zip(&self.policy_bias)
+            .map(|(weights, bias)| {
+                let sum: f64 = weights.iter().zip(hidden).map(|(w, h)| w * h).sum();
+                sum + bias
+            })
+            .collect();
+        let max = logits.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        let exp: Vec<f64> = logits.iter().map(|l| (l - max).exp()).collect();
+        let total: f64 = exp.iter().sum();
+        exp.iter().map(|e| e / total).collect()
+    }
+
+    fn value(&self, hidden: &[f64]) -> f64 {
+        let sum: f64 = self
+            .value_weights
+            .iter()
+            .zip(hidden)
+            .map(|(w, h)| w * h)
+            .sum();
+        (sum + self.value_bias).tanh()
+    }
+
+    // Backpropagation, the policy head is only trained with a policy target
+    fn train(&mut self, features: &[f64], policy: Option<&[f64]>, value: f64, learning_rate: f64) {
+        let hidden = self.hidden(features);
+        let prediction = self.value(&hidden);
+        let value_gradient = 2.0 * (prediction - value) * (1.0 - prediction * prediction);
+        let mut hidden_gradients: Vec<f64> = self
+            .value_weights
+            .iter()
+            .map(|w| w * value_gradient)
+            .collect();
+
+        for (i, h) in hidden.iter().enumerate() {
+            self.value_weights[i] -= learning_rate * value_gradient * h;
+        }
+        self.value_bias -= learning_rate * value_gradient;
+
+        if let Some(target) = policy {
+            // Gradient of softmax with cross entropy
+            let logit_gradients: Vec<f64> = self
+                .policy(&hidden)
+                .iter()
+                .zip(target)
+                .map(|(p, t)| p - t)
+                .collect();
+            for (m, gradient) in logit_gradients.iter().enumerate() {
+                for (i, h) in hidden.iter().enumerate() {
+                    hidden_gradients[i] += self.policy_weights[m][i] * gradient;
+                    self.policy_weights[m][i] -= learning_rate * gradient * h;
+                }
+                self.policy_bias[m] -= learning_rate * gradient;
+            }
+        }
+
+        for (i, h) in hidden.iter().enumerate() {
+            let gradient = hidden_gradients[i] * (1.0 - h * h);
+            for (w, f) in self.hidden_weights[i].iter_mut().zip(features) {
+                *w -= learning_rate * gradient * f;
+            }
+            self.hidden_bias[i] -= learning_rate * gradient;
+        }
+    }
+}
+
+impl PolicyValueModel for PolicyValueMlp {
+    fn predict_policy_value(&self, features: &[f64]) -> (Vec<f64>, f64) {
+        let hidden = self.hidden(features);
+        (self.policy(&hidden), self.value(&hidden))
+    }
+
+    fn train_policy_sample(&mut self, sample: &PolicySample, learning_rate: f64) {
+        self.train(
+            &sample.features,
+            Some(&sample.policy),
+            sample.value,
+            learning_rate,
+        );
+    }
+}
+
+impl ValueModel for PolicyValueMlp {
+    fn predict(&self, features: &[f64]) -> f64 {
+        self.value(&self.hidden(features))
+    }
+
+    fn train_sample(&mut self, sample: &Sample, learning_rate: f64) {
+        self.train(&sample.features, None, sample.target, learning_rate);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn learns_policy_and_value() {
+        // Setup
+        let samples = vec![
+            PolicySample {
+                features: vec![1.0, 0.0],
+                policy: vec![0.8, 0.2, 0.0],
+                value: 0.5,
+            },
+            PolicySample {
+                features: vec![0.0, 1.0],
+                policy: vec![0.0, 0.0, 1.0],
+                value: -0.5,
+            },
+        ];
+        let mut random = Random::new(5);
+        let mut model = PolicyValueMlp::new(2, 4, 3, &mut random);
+
+        // Act
+        model.train_policy(&samples, 2000, 0.05, &mut random);
+
+        // Test
+        for sample in &samples {
+            let (policy, value) = model.predict_policy_value(&sample.features);
+            assert!((policy.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+            for (p, t) in policy.iter().zip(&sample.policy) {
+                assert!((p - t).abs() < 0.1);
+            }
+            assert!((value - sample.value).abs() < 0.1);
+        }
+    }
+}
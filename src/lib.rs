@@ -1,6 +1,16 @@
+pub mod best_first;
+pub mod cfr;
+pub mod learning;
 pub mod model;
 pub mod negamax;
+pub mod opening_book;
+pub mod proof_number;
+pub mod search_trace;
+pub mod solver;
+pub mod tablebase;
+pub mod testing;
 pub mod tournament;
+pub mod tuning;
 pub mod tools;
 
 #[cfg(test)]
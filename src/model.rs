@@ -118,6 +118,7 @@ impl Player {
 }
 
 /** All the results a move can have!*/
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
 pub enum MoveOutcome {
     /// A player has won
     PlayerWon(Player),
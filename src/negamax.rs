@@ -1,50 +1,216 @@
 use crate::model::MoveOutcome;
 use crate::model::{self, MoveIterator};
+use crate::solver::GameValue;
+use crate::tablebase::TablebaseProbe;
+use crate::tools::random::Random;
 
-const MAX_SCORE: i64 = i64::MAX - 2;
+pub(crate) const MAX_SCORE: i64 = i64::MAX - 2;
 /** Public interface if the negamax function, sets some defaults. See [negamax_impl]*/
 pub fn negamax<GS>(gs: &mut GS, depth: i8) -> (i64, Option<GS::Move>)
 where
     GS: model::MiniMaxGameState,
 {
-    negamax_impl(gs, depth, -MAX_SCORE - 1, MAX_SCORE + 1)
+    negamax_with_observer(gs, depth, &mut ())
+}
+
+/** Same as [negamax], but reports every visited node to the observer. */
+pub fn negamax_with_observer<GS, O>(
+    gs: &mut GS,
+    depth: i8,
+    observer: &mut O,
+) -> (i64, Option<GS::Move>)
+where
+    GS: model::MiniMaxGameState,
+    O: SearchObserver<GS::Move>,
+{
+    negamax_root(gs, depth, &(), observer, None)
+}
+
+/** Same as [negamax], but if several moves have the best score, one of them is chosen at
+ * random instead of the first one. With the same seed, the same move is chosen.
+ */
+pub fn negamax_with_random_ties<GS>(
+    gs: &mut GS,
+    depth: i8,
+    random: &mut Random,
+) -> (i64, Option<GS::Move>)
+where
+    GS: model::MiniMaxGameState,
+{
+    negamax_root(gs, depth, &(), &mut (), Some(random))
+}
+
+/** Same as [negamax], but also counts the expanded nodes and evaluations. */
+pub fn negamax_with_statistics<GS>(gs: &mut GS, depth: i8) -> (i64, Option<GS::Move>, SearchStatistics)
+where
+    GS: model::MiniMaxGameState,
+{
+    let mut statistics = SearchStatistics::default();
+    let (score, best_move) = negamax_with_observer(gs, depth, &mut statistics);
+    (score, best_move, statistics)
+}
+
+/** Same as [negamax], but positions known to the tablebase are not searched, their exact value
+ * is used instead. Wins (and losses) are scored a bit below (above) the score of an immediate
+ * win, so that the shortest win is preferred.
+ */
+pub fn negamax_with_tablebase<GS, P>(
+    gs: &mut GS,
+    depth: i8,
+    tablebase: &P,
+) -> (i64, Option<GS::Move>)
+where
+    GS: model::MiniMaxGameState,
+    P: TablebaseProbe<GS>,
+{
+    negamax_root(gs, depth, tablebase, &mut (), None)
+}
+
+fn negamax_root<GS, P, O>(
+    gs: &mut GS,
+    depth: i8,
+    tablebase: &P,
+    observer: &mut O,
+    tie_breaker: Option<&mut Random>,
+) -> (i64, Option<GS::Move>)
+where
+    GS: model::MiniMaxGameState,
+    P: TablebaseProbe<GS>,
+    O: SearchObserver<GS::Move>,
+{
+    let (alpha, beta) = (-MAX_SCORE - 1, MAX_SCORE + 1);
+    observer.enter_node(None, depth, alpha, beta);
+    let (score, best_move, cutoff) =
+        negamax_impl(gs, depth, alpha, beta, tablebase, observer, tie_breaker);
+    observer.leave_node(score, cutoff);
+    (score, best_move)
+}
+
+/// The negamax score of an exactly known value.
+fn score_for_value(value: GameValue) -> i64 {
+    match value {
+        GameValue::Win(d) => MAX_SCORE - 1 - i64::from(d),
+        GameValue::Loss(d) => -MAX_SCORE + 1 + i64::from(d),
+        GameValue::Draw(_) => 0,
+    }
+}
+
+/** Gets notified while negamax walks the game tree, for example to record the explored tree
+ * (see [crate::search_trace]).
+ *
+ * Every visited node is reported with `enter_node` and later `leave_node`, nodes entered in
+ * between are its children. Scores and the alpha-beta window are from the perspective of the
+ * player who made the move leading to the node (for the root, the active player).
+ */
+pub trait SearchObserver<M> {
+    /// A node is entered, `m` is the move leading to it (`None` for the root).
+    fn enter_node(&mut self, _m: Option<&M>, _depth: i8, _alpha: i64, _beta: i64) {}
+    /// The last entered node is left, `cutoff` is set if the search was pruned there.
+    fn leave_node(&mut self, _score: i64, _cutoff: bool) {}
+    /// The evaluation function was called for the last entered node.
+    fn evaluated(&mut self, _score: i64) {}
+    /// The moves of the last entered node are generated, it is not a leaf of the search.
+    fn expanded(&mut self) {}
+}
+
+/// The observer doing nothing.
+impl<M> SearchObserver<M> for () {}
+
+/** Counters describing the work of a search, to compare the efficiency of search algorithms
+ * (see also [crate::best_first]).
+ */
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SearchStatistics {
+    /** Expanded nodes, including the root: the positions whose moves were generated. Leaves,
+     * that are evaluated or end the game, are not counted. All algorithms count the same, so
+     * the numbers can be compared.
+     */
+    pub nodes: u64,
+    /// Calls of the evaluation function
+    pub evaluations: u64,
+    /// Subtrees that were pruned
+    pub cutoffs: u64,
+}
+
+impl<M> SearchObserver<M> for SearchStatistics {
+    fn leave_node(&mut self, _score: i64, cutoff: bool) {
+        if cutoff {
+            self.cutoffs += 1;
+        }
+    }
+
+    fn evaluated(&mut self, _score: i64) {
+        self.evaluations += 1;
+    }
+
+    fn expanded(&mut self) {
+        self.nodes += 1;
+    }
 }
 
 /** Implementation of the negamax algorithm (https://www.chessprogramming.org/Negamax)
  * based on the turn based model introduces in the model module.
+ *
+ * Returns the score, the best move and if the search was cut off by alpha-beta pruning.
+ * If a tie breaker is given, equally scored moves are chosen at random (only used at the root).
 */
-fn negamax_impl<GS>(
+fn negamax_impl<GS, P, O>(
     gs: &mut GS,
     mut depth: i8,
     mut alpha: i64,
     beta: i64,
-) -> (i64, Option<GS::Move>)
+    tablebase: &P,
+    observer: &mut O,
+    mut tie_breaker: Option<&mut Random>,
+) -> (i64, Option<GS::Move>, bool)
 where
     GS: model::MiniMaxGameState,
+    P: TablebaseProbe<GS>,
+    O: SearchObserver<GS::Move>,
 {
     let active_player = gs.active_player();
 
     if depth == 0 {
-        return (gs.evaluate(&active_player), None);
+        let score = gs.evaluate(&active_player);
+        observer.evaluated(score);
+        return (score, None, false);
     }
     let mut best_score: i64 = -MAX_SCORE - 1;
     let mut best_move: Option<GS::Move> = None;
+    let mut best_moves_count = 0;
 
+    observer.expanded();
     let mut move_iterator = gs.move_iterator();
     while let Some(m) = move_iterator.next(gs) {
-        let score = match gs.apply_move(&m) {
+        observer.enter_node(Some(m), depth - 1, alpha, beta);
+        let (score, cutoff) = match gs.apply_move(&m) {
             MoveOutcome::PlayerWon(p) => {
                 depth = 0;
                 if p == active_player {
-                    MAX_SCORE
+                    (MAX_SCORE, false)
                 } else {
-                    -MAX_SCORE
+                    (-MAX_SCORE, false)
                 }
             }
-            MoveOutcome::Tie => 0,
-            MoveOutcome::SwitchPlayer(_p) => -negamax_impl(gs, depth - 1, -beta, -alpha).0,
-            MoveOutcome::ContinuePlayer(_p) => negamax_impl(gs, depth - 1, alpha, beta).0,
+            MoveOutcome::Tie => (0, false),
+            MoveOutcome::SwitchPlayer(_p) => match tablebase.probe(gs) {
+                Some(value) => (score_for_value(value.one_move_earlier(true)), false),
+                None => {
+                    let (score, _, cutoff) =
+                        negamax_impl(gs, depth - 1, -beta, -alpha, tablebase, observer, None);
+                    (-score, cutoff)
+                }
+            },
+            MoveOutcome::ContinuePlayer(_p) => match tablebase.probe(gs) {
+                Some(value) => (score_for_value(value.one_move_earlier(false)), false),
+                None => {
+                    let (score, _, cutoff) =
+                        negamax_impl(gs, depth - 1, alpha, beta, tablebase, observer, None);
+                    (score, cutoff)
+                }
+            },
         };
+        observer.leave_node(score, cutoff);
         // Undo the move
         gs.reverse_move(m);
 
@@ -52,24 +218,39 @@ where
         if score > best_score {
             best_score = score;
             best_move = Some(m.clone());
+            best_moves_count = 1;
+        } else if score == best_score {
+            if let Some(random) = tie_breaker.as_deref_mut() {
+                // Every equally scored move gets the same chance (reservoir sampling)
+                best_moves_count += 1;
+                if random.below(best_moves_count) == 0 {
+                    best_move = Some(m.clone());
+                }
+            }
         }
 
         // If the depth was reduced to 0, stop!
         if depth == 0 {
-            return (best_score, best_move);
+            return (best_score, best_move, false);
         }
 
-        // Adjust alpha
-        if best_score > alpha {
-            alpha = best_score;
+        // Adjust alpha. When breaking ties, keep the best score inside the window, so that the
+        // scores of equally good moves are exact.
+        let new_alpha = if tie_breaker.is_some() {
+            best_score - 1
+        } else {
+            best_score
+        };
+        if new_alpha > alpha {
+            alpha = new_alpha;
         }
 
         // Alpha-beta pruning
         if alpha >= beta {
-            return (alpha, best_move);
+            return (alpha, best_move, true);
         }
     }
-    return (best_score, best_move);
+    return (best_score, best_move, false);
 }
 
 #[cfg(test)]
@@ -77,7 +258,11 @@ mod tests {
     use crate::model::MoveOutcome::{PlayerWon, SwitchPlayer};
     use crate::model::{self, MoveIterator};
     use crate::model::{MiniMaxGameState, MoveOutcome, Player};
-    use crate::negamax::{negamax, MAX_SCORE};
+    use crate::negamax::{negamax, negamax_with_random_ties, negamax_with_tablebase, MAX_SCORE};
+    use crate::solver::solve;
+    use crate::tablebase::Tablebase;
+    use crate::testing::test_games::NimGameState;
+    use crate::tools::random::Random;
 
     // Mock for a game model
     struct CenterScoreWinMovePathGame {
@@ -246,4 +431,69 @@ mod tests {
         // Test
         assert_eq!(m, Some(10));
     }
+
+    #[test]
+    fn use_tablebase_values() {
+        // Setup
+        let mut gs = NimGameState::new(10);
+        let tablebase = Tablebase::from_solved(&solve(&gs));
+
+        // Act
+        // Nim has no evaluation, so without the tablebase depth 1 is a guess
+        let (s, m) = negamax_with_tablebase(&mut gs, 1, &tablebase);
+
+        // Test
+        // Taking 1 leaves 9 stones, a loss in 6 moves for the opponent
+        assert_eq!(m, Some(1));
+        assert_eq!(s, MAX_SCORE - 1 - 7);
+    }
+
+    #[test]
+    fn random_tie_breaks() {
+        // Setup
+        let mut gs = CenterScoreWinMovePathGame::new(vec![], None);
+
+        // Act
+        // Moves 9 and 11 score the same, so do 8 and 12, ... but 10 scores best
+        let mut moves = Vec::new();
+        for seed in 0..20 {
+            let (s, m) = negamax_with_random_ties(&mut gs, 1, &mut Random::new(seed));
+            assert_eq!(s, 10);
+            moves.push(m.unwrap());
+        }
+
+        // Test
+        assert!(moves.iter().all(|m| *m == 10));
+    }
+
+    #[test]
+    fn random_tie_breaks_between_equal_moves() {
+        // Setup
+        let mut gs = NimGameState::new(10);
+
+        // Act
+        // Without evaluation, both moves score 0 at depth 1
+        let moves: Vec<u32> = (0..20)
+            .map(|seed| {
+                negamax_with_random_ties(&mut gs, 1, &mut Random::new(seed))
+                    .1
+                    .unwrap()
+            })
+            .collect();
+
+        // Test
+        assert!(moves.contains(&1));
+        assert!(moves.contains(&2));
+        assert_eq!(
+            negamax_with_random_ties(&mut gs, 1, &mut Random::new(3)),
+            negamax_with_random_ties(&mut gs, 1, &mut Random::new(3))
+        );
+        // Deep enough, only taking 1 wins
+        for seed in 0..5 {
+            assert_eq!(
+                negamax_with_random_ties(&mut gs, 8, &mut Random::new(seed)).1,
+                Some(1)
+            );
+        }
+    }
 }
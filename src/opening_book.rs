@@ -0,0 +1,327 @@
+/*! Opening books: weighted moves for known positions, played instead of searching.
+ *
+ * Positions are identified by a hash and moves by a short notation, both provided by the game
+ * via [BookPosition]. Books are stored in a simple text format, one move per line:
+ *
+ * ```text
+ * # position hash (hex)  move  weight
+ * 0000000000000000 e4 10
+ * 0000000000000000 d4 5
+ * ```
+ *
+ * [OpeningBookBuilder] builds books from recorded games, for example from
+ * [crate::tournament::ki_battle_with_options] self-play. The connect-four example builds a book
+ * this way and plays with it.
+ */
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use crate::model::{GameState, MoveIterator, MoveOutcome, Player};
+use crate::tools::random::Random;
+
+/// What a game has to provide, to be used with opening books.
+pub trait BookPosition: GameState {
+    /// A hash identifying the position. It must be stable between program runs.
+    fn position_hash(&self) -> u64;
+    /// The notation of a move in this position. It must not contain whitespace.
+    fn move_notation(&self, m: &Self::Move) -> String;
+}
+
+/// Something that may know a good move for a position.
+pub trait BookProbe<GS: GameState> {
+    fn book_move(&self, gs: &GS, random: &mut Random) -> Option<GS::Move>;
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BookEntry {
+    pub notation: String,
+    pub weight: u64,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct OpeningBook {
+    entries: HashMap<u64, Vec<BookEntry>>,
+}
+
+impl OpeningBook {
+    pub fn new() -> OpeningBook {
+        OpeningBook {
+            entries: HashMap::new(),
+        }
+    }
+
+    /// Number of positions in the book
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /** Adds the weight to the move in the position, the move is added if it is new.
+     * The weight is clamped, so that the weights of a position sum up to at most `u64::MAX`,
+     * as [Random::weighted_index] requires.
+     */
+    pub fn add(&mut self, hash: u64, notation: &str, weight: u64) {
+        let weight = weight.min(u64::MAX - self.total_weight(hash));
+        let moves = self.entries.entry(hash).or_default();
+        match moves.iter_mut().find(|e| e.notation == notation) {
+            Some(entry) => entry.weight += weight,
+            None => moves.push(BookEntry {
+                notation: String::from(notation),
+                weight,
+            }),
+        }
+    }
+
+    /// The moves known for the position
+    pub fn entries(&self, hash: u64) -> &[BookEntry] {
+        self.entries.get(&hash).map_or(&[], |e| e.as_slice())
+    }
+
+    // The sum of the weights of the moves in the position, it never overflows, see add
+    fn total_weight(&self, hash: u64) -> u64 {
+        self.entries(hash).iter().map(|e| e.weight).sum()
+    }
+
+    /** Picks one of the book moves of the position, with a probability proportional to its
+     * weight. Returns `None` if the position is not in the book or none of its moves is legal.
+     */
+    pub fn choose_move<GS: BookPosition>(&self, gs: &GS, random: &mut Random) -> Option<GS::Move> {
+        let entries = self.entries(gs.position_hash());
+        if entries.is_empty() {
+            return None;
+        }
+        let mut candidates = Vec::new();
+        let mut weights = Vec::new();
+        let mut move_iterator = gs.move_iterator();
+        while let Some(m) = move_iterator.next(gs) {
+            let notation = gs.move_notation(m);
+            if let Some(entry) = entries.iter().find(|e| e.notation == notation) {
+                candidates.push(m.clone());
+                weights.push(entry.weight);
+            }
+        }
+        random
+            .weighted_index(&weights)
+            .map(|index| candidates.swap_remove(index))
+    }
+
+    pub fn read_text<R: BufRead>(reader: R) -> io::Result<OpeningBook> {
+        let mut book = OpeningBook::new();
+        for (line_number, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = || {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Invalid opening book entry in line {}", line_number + 1),
+                )
+            };
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            if parts.len() != 3 {
+                return Err(invalid());
+            }
+            let hash = u64::from_str_radix(parts[0], 16).map_err(|_| invalid())?;
+            let weight = parts[2].parse::<u64>().map_err(|_| invalid())?;
+            if book.total_weight(hash).checked_add(weight).is_none() {
+                return Err(invalid());
+            }
+            book.add(hash, parts[1], weight);
+        }
+        Ok(book)
+    }
+
+    /// Writes the book in the text format, sorted by position hash
+    pub fn write_text<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        let mut hashes: Vec<&u64> = self.entries.keys().collect();
+        hashes.sort();
+        for hash in hashes {
+            for entry in &self.entries[hash] {
+                writeln!(w, "{:016x} {} {}", hash, entry.notation, entry.weight)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<OpeningBook> {
+        OpeningBook::read_text(BufReader::new(File::open(path)?))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_text(&mut writer)?;
+        writer.flush()
+    }
+}
+
+impl<GS: BookPosition> BookProbe<GS> for OpeningBook {
+    fn book_move(&self, gs: &GS, random: &mut Random) -> Option<GS::Move> {
+        self.choose_move(gs, random)
+    }
+}
+
+/** Builds an opening book from recorded games.
+ *
+ * Every move of the first `max_plies` plies of a game gets a weight by the result of the game,
+ * for the player who made it: 2 for a win, 1 for a tie, 0 for a loss.
+ * So moves that won more often are played more often.
+ */
+pub struct OpeningBookBuilder {
+    max_plies: usize,
+    book: OpeningBook,
+}
+
+impl OpeningBookBuilder {
+    pub fn new(max_plies: usize) -> OpeningBookBuilder {
+        OpeningBookBuilder {
+            max_plies,
+            book: OpeningBook::new(),
+        }
+    }
+
+    /// Adds a game, given by its initial state, its moves and the winner (`None` for a tie).
+    pub fn add_game<GS>(&mut self, initial: &GS, moves: &[GS::Move], winner: Option<Player>)
+    where
+        GS: BookPosition + Clone,
+    {
+        let mut gs = initial.clone();
+        for m in moves.iter().take(self.max_plies) {
+            let player = gs.active_player();
+            let weight = match winner {
+                Some(p) if p == player => 2,
+                Some(_) => 0,
+                None => 1,
+            };
+            self.book
+                .add(gs.position_hash(), &gs.move_notation(m), weight);
+            if let MoveOutcome::PlayerWon(_) | MoveOutcome::Tie = gs.apply_move(m) {
+                break;
+            }
+        }
+    }
+
+    /// The book, without moves that never scored
+    pub fn build(mut self) -> OpeningBook {
+        for moves in self.book.entries.values_mut() {
+            moves.retain(|e| e.weight > 0);
+        }
+        self.book.entries.retain(|_, moves| !moves.is_empty());
+        self.book
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing::test_games::NimGameState;
+
+    // The hash is unique for piles below 2^63 stones
+    impl BookPosition for NimGameState {
+        fn position_hash(&self) -> u64 {
+            let player = match self.active_player {
+                Player::Player1 => 0,
+                Player::Player2 => 1,
+            };
+            u64::from(self.stones) * 2 + player
+        }
+
+        fn move_notation(&self, m: &u32) -> String {
+            format!("take{}", m)
+        }
+    }
+
+    #[test]
+    fn choose_weighted_book_moves() {
+        // Setup
+        let gs = NimGameState::new(10);
+        let mut book = OpeningBook::new();
+        book.add(gs.position_hash(), "take1", 3);
+        book.add(gs.position_hash(), "take2", 0);
+        book.add(gs.position_hash(), "take3", 100); // Not a legal move
+        let mut random = Random::new(1);
+
+        // Act & Test
+        for _ in 0..20 {
+            assert_eq!(book.choose_move(&gs, &mut random), Some(1));
+        }
+        assert_eq!(book.choose_move(&NimGameState::new(9), &mut random), None);
+    }
+
+    #[test]
+    fn write_and_read_text() {
+        // Setup
+        let mut book = OpeningBook::new();
+        book.add(0x1234, "take1", 3);
+        book.add(0x1234, "take2", 1);
+        book.add(0xffff_ffff_ffff_ffff, "take1", 7);
+        let mut text = Vec::new();
+
+        // Act
+        book.write_text(&mut text).unwrap();
+        let read = OpeningBook::read_text(text.as_slice()).unwrap();
+
+        // Test
+        assert_eq!(
+            String::from_utf8(text).unwrap(),
+            "0000000000001234 take1 3\n0000000000001234 take2 1\nffffffffffffffff take1 7\n"
+        );
+        assert_eq!(read, book);
+    }
+
+    #[test]
+    fn reject_invalid_text() {
+        let text = "# comment\n\n0000000000001234 take1\n";
+        assert!(OpeningBook::read_text(text.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn clamp_and_reject_overflowing_weights() {
+        // Setup
+        let mut book = OpeningBook::new();
+        let text = "0000000000001234 take1 18446744073709551615\n0000000000001234 take2 1\n";
+
+        // Act
+        book.add(0x1234, "take1", u64::MAX - 1);
+        book.add(0x1234, "take2", 5);
+        book.add(0x1234, "take1", 5);
+
+        // Test
+        assert_eq!(book.entries(0x1234)[0].weight, u64::MAX - 1);
+        assert_eq!(book.entries(0x1234)[1].weight, 1);
+        assert!(OpeningBook::read_text(text.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn build_from_games() {
+        // Setup
+        let gs = NimGameState::new(4);
+        let mut builder = OpeningBookBuilder::new(2);
+
+        // Act
+        // Player1 wins by taking 1, then taking the last 2 stones
+        builder.add_game(&gs, &[1, 1, 2], Some(Player::Player1));
+        // Player2 wins by answering 2 with 2
+        builder.add_game(&gs, &[2, 2], Some(Player::Player2));
+        let book = builder.build();
+
+        // Test
+        assert_eq!(
+            book.entries(gs.position_hash()),
+            &[BookEntry {
+                notation: String::from("take1"),
+                weight: 2
+            }]
+        );
+        let mut after_take2 = gs.clone();
+        after_take2.apply_move(&2);
+        assert_eq!(book.entries(after_take2.position_hash())[0].weight, 2);
+        assert_eq!(book.len(), 2);
+    }
+}
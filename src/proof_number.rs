@@ -0,0 +1,277 @@
+/*! Proof-number search (https://www.chessprogramming.org/Proof-Number_Search) and its two
+ * level variant PN², for proving (or disproving) that the active player can force a win.
+ *
+ * Only the [MoveOutcome] of the moves is used, no evaluation: `PlayerWon` of the attacker
+ * proves a node, a `Tie` or a win of the opponent disproves it.
+ */
+use crate::model::{GameState, MoveIterator, MoveOutcome, Player};
+
+const INFINITY: u64 = u64::MAX;
+
+/// The result of a proof-number search.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ProofResult<M> {
+    /// The active player can force a win, starting with this move
+    Proven(M),
+    /// The active player can not force a win
+    Disproven,
+    /// The node limit was reached before the question was answered
+    Unknown,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProofSearch<M> {
+    pub result: ProofResult<M>,
+    /// Number of nodes created in the (first level) tree
+    pub nodes: usize,
+    /// Number of nodes created in second level searches (only for PN²)
+    pub second_level_nodes: usize,
+}
+
+struct Node<M> {
+    m: Option<M>,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    proof: u64,
+    disproof: u64,
+    // The attacker is to move in this node
+    or_node: bool,
+    expanded: bool,
+}
+
+/** Proof-number search, creating at most `max_nodes` nodes. */
+pub fn pn_search<GS: GameState>(gs: &mut GS, max_nodes: usize) -> ProofSearch<GS::Move> {
+    let attacker = gs.active_player();
+    let (tree, second_level_nodes) = search_tree(gs, attacker, max_nodes, false);
+    result_of(tree, second_level_nodes)
+}
+
+/** PN² search: the children of every expanded node are initialized by a second proof-number
+ * search, limited to the current size of the tree. This needs much less memory than
+ * [pn_search], at the cost of more time. At most `max_nodes` nodes are stored in the first
+ * level tree.
+ */
+pub fn pn2_search<GS: GameState>(gs: &mut GS, max_nodes: usize) -> ProofSearch<GS::Move> {
+    let attacker = gs.active_player();
+    let (tree, second_level_nodes) = search_tree(gs, attacker, max_nodes, true);
+    result_of(tree, second_level_nodes)
+}
+
+fn result_of<M: Clone>(tree: Vec<Node<M>>, second_level_nodes: usize) -> ProofSearch<M> {
+    let root = &tree[0];
+    let result = if root.proof == 0 {
+        let winning_child = root
+            .children
+            .iter()
+            .find(|c| tree[**c].proof == 0)
+            .expect("A proven OR node has a proven child");
+        ProofResult::Proven(
+            tree[*winning_child]
+                .m
+                .clone()
+                .expect("Only the root has no move"),
+        )
+    } else if root.disproof == 0 {
+        ProofResult::Disproven
+    } else {
+        ProofResult::Unknown
+    };
+    ProofSearch {
+        result,
+        nodes: tree.len(),
+        second_level_nodes,
+    }
+}
+
+// Builds the proof tree, returns it and the number of second level nodes.
+fn search_tree<GS: GameState>(
+    gs: &mut GS,
+    attacker: Player,
+    max_nodes: usize,
+    two_level: bool,
+) -> (Vec<Node<GS::Move>>, usize) {
+    let mut tree = vec![Node {
+        m: None,
+        parent: None,
+        children: Vec::new(),
+        proof: 1,
+        disproof: 1,
+        or_node: gs.active_player() == attacker,
+        expanded: false,
+    }];
+    let mut second_level_nodes = 0;
+    while tree[0].proof != 0 && tree[0].disproof != 0 && tree.len() < max_nodes {
+        // Go down to the most proving node
+        let mut current = 0;
+        while tree[current].expanded {
+            let node = &tree[current];
+            let next = *node
+                .children
+                .iter()
+                .min_by_key(|c| {
+                    if node.or_node {
+                        tree[**c].proof
+                    } else {
+                        tree[**c].disproof
+                    }
+                })
+                .expect("Unsolved expanded nodes have children");
+            gs.apply_move(tree[next].m.as_ref().expect("Only the root has no move"));
+            current = next;
+        }
+
+        let second_level_limit = if two_level { Some(tree.len()) } else { None };
+        second_level_nodes += expand(&mut tree, current, gs, attacker, second_level_limit);
+
+        // Update the numbers up to the root
+        loop {
+            update_numbers(&mut tree, current);
+            match tree[current].parent {
+                Some(parent) => {
+                    gs.reverse_move(tree[current].m.as_ref().expect("Only the root has no move"));
+                    current = parent;
+                }
+                None => break,
+            }
+        }
+    }
+    (tree, second_level_nodes)
+}
+
+// Creates the children of the node, returns the number of second level nodes used.
+fn expand<GS: GameState>(
+    tree: &mut Vec<Node<GS::Move>>,
+    node: usize,
+    gs: &mut GS,
+    attacker: Player,
+    second_level_limit: Option<usize>,
+) -> usize {
+    let mut second_level_nodes = 0;
+    let mut move_iterator = gs.move_iterator();
+    while let Some(m) = move_iterator.next(gs) {
+        let (proof, disproof, terminal) = match gs.apply_move(m) {
+            MoveOutcome::PlayerWon(p) if p == attacker => (0, INFINITY, true),
+            MoveOutcome::PlayerWon(_) | MoveOutcome::Tie => (INFINITY, 0, true),
+            MoveOutcome::SwitchPlayer(_) | MoveOutcome::ContinuePlayer(_) => {
+                match second_level_limit {
+                    Some(limit) => {
+                        let (child_tree, _) = search_tree(gs, attacker, limit.max(2), false);
+                        second_level_nodes += child_tree.len();
+                        (child_tree[0].proof, child_tree[0].disproof, false)
+                    }
+                    None => (1, 1, false),
+                }
+            }
+        };
+        let or_node = gs.active_player() == attacker;
+        gs.reverse_move(m);
+        tree.push(Node {
+            m: Some(m.clone()),
+            parent: Some(node),
+            children: Vec::new(),
+            proof,
+            disproof,
+            or_node,
+            expanded: terminal,
+        });
+        let child = tree.len() - 1;
+        tree[node].children.push(child);
+    }
+    tree[node].expanded = true;
+    second_level_nodes
+}
+
+fn update_numbers<M>(tree: &mut [Node<M>], node: usize) {
+    if tree[node].children.is_empty() {
+        if tree[node].expanded && tree[node].proof != 0 && tree[node].disproof != 0 {
+            // No moves, but no end of the game either: no win possible
+            tree[node].proof = INFINITY;
+            tree[node].disproof = 0;
+        }
+        return;
+    }
+    let proofs = tree[node].children.iter().map(|c| tree[*c].proof);
+    let disproofs = tree[node].children.iter().map(|c| tree[*c].disproof);
+    let (proof, disproof) = if tree[node].or_node {
+        (
+            proofs.min().unwrap_or(INFINITY),
+            disproofs.fold(0, u64::saturating_add),
+        )
+    } else {
+        (
+            proofs.fold(0, u64::saturating_add),
+            disproofs.min().unwrap_or(INFINITY),
+        )
+    };
+    tree[node].proof = proof;
+    tree[node].disproof = disproof;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing::test_games::NimGameState;
+
+    #[test]
+    fn prove_win() {
+        // Setup
+        let mut gs = NimGameState::new(10);
+
+        // Act
+        let search = pn_search(&mut gs, 10_000);
+
+        // Test
+        assert_eq!(search.result, ProofResult::Proven(1));
+        assert_eq!(gs, NimGameState::new(10));
+    }
+
+    #[test]
+    fn disprove_win() {
+        // Setup
+        let mut gs = NimGameState::new(9);
+
+        // Act
+        let search = pn_search(&mut gs, 10_000);
+
+        // Test
+        assert_eq!(search.result, ProofResult::Disproven);
+        assert_eq!(gs, NimGameState::new(9));
+    }
+
+    #[test]
+    fn stop_at_node_limit() {
+        // Setup
+        let mut gs = NimGameState::new(30);
+
+        // Act
+        let search = pn_search(&mut gs, 10);
+
+        // Test
+        assert_eq!(search.result, ProofResult::Unknown);
+        assert!(search.nodes <= 10 + 2);
+        assert_eq!(gs, NimGameState::new(30));
+    }
+
+    #[test]
+    fn pn2_proves_with_smaller_tree() {
+        // Setup
+        let mut gs = NimGameState::new(20);
+
+        // Act
+        let pn = pn_search(&mut gs, 100_000);
+        let pn2 = pn2_search(&mut gs, 100_000);
+
+        // Test
+        assert_eq!(pn.result, ProofResult::Proven(2));
+        assert_eq!(pn2.result, ProofResult::Proven(2));
+        assert!(pn2.nodes < pn.nodes);
+        assert!(pn2.second_level_nodes > 0);
+        assert_eq!(gs, NimGameState::new(20));
+    }
+
+    #[test]
+    fn pn2_disproves() {
+        let mut gs = NimGameState::new(12);
+        assert_eq!(pn2_search(&mut gs, 100_000).result, ProofResult::Disproven);
+    }
+}
@@ -0,0 +1,260 @@
+/*! Records the game tree explored by [crate::negamax] and exports it as Graphviz DOT file.
+ *
+ * This is meant for debugging the search on small games, like tic-tac-toe:
+ ```no_run
+  use turn_based_games::model::MiniMaxGameState;#[doc(hidden)]
+  use turn_based_games::search_trace::negamax_traced;#[doc(hidden)]
+  fn write_tree<GS: MiniMaxGameState>(gs: &mut GS) where GS::Move: std::fmt::Debug {
+      let (_score, _best_move, trace) = negamax_traced(gs, 4, 1000);
+      let mut file = std::fs::File::create("search.dot").unwrap();
+      trace.write_dot(&mut file).unwrap();
+  }
+  ```
+ * The file can be rendered with `dot -Tsvg search.dot -o search.svg`.
+ */
+use std::fmt::Debug;
+use std::io::{self, Write};
+
+use crate::model::MiniMaxGameState;
+use crate::negamax::{negamax_with_observer, SearchObserver, MAX_SCORE};
+
+/// A node of the recorded search tree.
+#[derive(Debug, Clone)]
+pub struct TraceNode {
+    /// Index of the parent node, `None` for the root
+    pub parent: Option<usize>,
+    /// The move leading to this node, formatted with `Debug`
+    pub move_label: Option<String>,
+    /// The remaining search depth in this node
+    pub depth: i8,
+    /// The alpha-beta window when the node was entered
+    pub alpha: i64,
+    pub beta: i64,
+    /// The score of the node, `None` if the node has not been left (yet)
+    pub score: Option<i64>,
+    /// The search was pruned in this node
+    pub cutoff: bool,
+}
+
+/** A [SearchObserver] recording up to `max_nodes` nodes of the search tree.
+ * Nodes beyond the limit are not recorded, but counted.
+ */
+pub struct SearchTrace {
+    nodes: Vec<TraceNode>,
+    // Indices of the currently entered nodes, None for nodes beyond the limit
+    stack: Vec<Option<usize>>,
+    max_nodes: usize,
+    skipped_nodes: usize,
+}
+
+impl SearchTrace {
+    pub fn new(max_nodes: usize) -> SearchTrace {
+        SearchTrace {
+            nodes: Vec::new(),
+            stack: Vec::new(),
+            max_nodes,
+            skipped_nodes: 0,
+        }
+    }
+
+    pub fn nodes(&self) -> &[TraceNode] {
+        &self.nodes
+    }
+
+    /// Number of nodes, that were visited after the limit was reached
+    pub fn skipped_nodes(&self) -> usize {
+        self.skipped_nodes
+    }
+
+    /** Writes the recorded tree in the DOT format. Edges are labeled with the moves, nodes
+     * with depth, alpha-beta window and score. Nodes with a cutoff are drawn red.
+     */
+    pub fn write_dot<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        writeln!(w, "digraph search {{")?;
+        writeln!(w, "  node [shape=box, fontname=\"monospace\"];")?;
+        for (index, node) in self.nodes.iter().enumerate() {
+            let score = node
+                .score
+                .map_or_else(|| String::from("?"), format_score);
+            write!(
+                w,
+                "  n{} [label=\"depth {}\\n[{}, {}]\\nscore {}\"",
+                index,
+                node.depth,
+                format_score(node.alpha),
+                format_score(node.beta),
+                score
+            )?;
+            if node.cutoff {
+                write!(w, ", color=red")?;
+            }
+            writeln!(w, "];")?;
+            if let Some(parent) = node.parent {
+                writeln!(
+                    w,
+                    "  n{} -> n{} [label=\"{}\"];",
+                    parent,
+                    index,
+                    escape(node.move_label.as_deref().unwrap_or(""))
+                )?;
+            }
+        }
+        if self.skipped_nodes > 0 {
+            writeln!(
+                w,
+                "  skipped [shape=plaintext, label=\"{} more nodes not recorded\"];",
+                self.skipped_nodes
+            )?;
+        }
+        writeln!(w, "}}")
+    }
+
+    pub fn to_dot(&self) -> String {
+        let mut output = Vec::new();
+        self.write_dot(&mut output)
+            .expect("Writing to a Vec does not fail");
+        String::from_utf8(output).expect("DOT output is valid UTF-8")
+    }
+}
+
+impl<M: Debug> SearchObserver<M> for SearchTrace {
+    fn enter_node(&mut self, m: Option<&M>, depth: i8, alpha: i64, beta: i64) {
+        if self.nodes.len() >= self.max_nodes {
+            self.skipped_nodes += 1;
+            self.stack.push(None);
+            return;
+        }
+        self.nodes.push(TraceNode {
+            parent: self.stack.last().copied().flatten(),
+            move_label: m.map(|m| format!("{:?}", m)),
+            depth,
+            alpha,
+            beta,
+            score: None,
+            cutoff: false,
+        });
+        self.stack.push(Some(self.nodes.len() - 1));
+    }
+
+    fn leave_node(&mut self, score: i64, cutoff: bool) {
+        if let Some(Some(index)) = self.stack.pop() {
+            self.nodes[index].score = Some(score);
+            self.nodes[index].cutoff = cutoff;
+        }
+    }
+}
+
+/** Runs [crate::negamax::negamax] and records up to `max_nodes` nodes of the explored tree. */
+pub fn negamax_traced<GS>(
+    gs: &mut GS,
+    depth: i8,
+    max_nodes: usize,
+) -> (i64, Option<GS::Move>, SearchTrace)
+where
+    GS: MiniMaxGameState,
+    GS::Move: Debug,
+{
+    let mut trace = SearchTrace::new(max_nodes);
+    let (score, best_move) = negamax_with_observer(gs, depth, &mut trace);
+    (score, best_move, trace)
+}
+
+// Scores beyond the score of a win are the "infinite" window of negamax
+fn format_score(score: i64) -> String {
+    if score > MAX_SCORE {
+        String::from("+inf")
+    } else if score == MAX_SCORE {
+        String::from("win")
+    } else if score == -MAX_SCORE {
+        String::from("loss")
+    } else if score < -MAX_SCORE {
+        String::from("-inf")
+    } else {
+        score.to_string()
+    }
+}
+
+fn escape(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::negamax::negamax;
+    use crate::testing::test_games::NimGameState;
+
+    #[test]
+    fn trace_does_not_change_result() {
+        // Setup
+        let mut gs = NimGameState::new(7);
+
+        // Act
+        let (score, best_move, trace) = negamax_traced(&mut gs, 6, 1000);
+
+        // Test
+        assert_eq!((score, best_move), negamax(&mut gs, 6));
+        assert_eq!(trace.nodes()[0].parent, None);
+        assert_eq!(trace.nodes()[0].score, Some(score));
+        assert!(trace.nodes().iter().all(|n| n.score.is_some()));
+        assert_eq!(trace.skipped_nodes(), 0);
+    }
+
+    #[test]
+    fn children_are_linked_to_parents() {
+        // Setup
+        let mut gs = NimGameState::new(2);
+
+        // Act
+        let (_, _, trace) = negamax_traced(&mut gs, 2, 1000);
+
+        // Test
+        // Root, take 1 (then take 1 to win) and take 2 (winning move, the search stops)
+        let parents: Vec<Option<usize>> = trace.nodes().iter().map(|n| n.parent).collect();
+        assert_eq!(parents, vec![None, Some(0), Some(1), Some(0)]);
+        assert_eq!(trace.nodes()[3].move_label, Some(String::from("2")));
+    }
+
+    #[test]
+    fn limit_number_of_nodes() {
+        // Setup
+        let mut gs = NimGameState::new(10);
+
+        // Act
+        let (_, _, trace) = negamax_traced(&mut gs, 8, 5);
+
+        // Test
+        assert_eq!(trace.nodes().len(), 5);
+        assert!(trace.skipped_nodes() > 0);
+        assert!(trace.to_dot().contains("more nodes not recorded"));
+    }
+
+    #[test]
+    fn format_wins_apart_from_window() {
+        assert_eq!(format_score(MAX_SCORE + 1), "+inf");
+        assert_eq!(format_score(MAX_SCORE), "win");
+        assert_eq!(format_score(17), "17");
+        assert_eq!(format_score(-MAX_SCORE), "loss");
+        assert_eq!(format_score(-MAX_SCORE - 1), "-inf");
+    }
+
+    #[test]
+    fn write_dot_file() {
+        // Setup
+        let mut gs = NimGameState::new(2);
+        let (_, _, trace) = negamax_traced(&mut gs, 2, 1000);
+
+        // Act
+        let dot = trace.to_dot();
+
+        // Test
+        assert!(dot.starts_with("digraph search {"));
+        assert!(dot.contains("n0 -> n1 [label=\"1\"];"));
+        assert!(dot.contains("n0 -> n3 [label=\"2\"];"));
+        // Taking the last 2 stones wins, the window of the root is unbounded
+        assert!(dot.contains("score win"));
+        assert!(dot.contains("[-inf, +inf]"));
+        assert!(!dot.contains("score +inf"));
+        assert!(dot.trim_end().ends_with('}'));
+    }
+}
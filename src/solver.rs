@@ -0,0 +1,396 @@
+/*! Exact solver for small games, based on retrograde analysis
+ * (https://www.chessprogramming.org/Retrograde_Analysis).
+ *
+ * All states reachable from an initial state are enumerated and stored in a hash map, so this
+ * only works for games with a small state space, like tic-tac-toe or connect-four on small
+ * boards. The values are then computed backwards from the ends of the game.
+ */
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use crate::model::{GameState, MoveIterator, MoveOutcome, Player};
+
+/** The exact value of a state for the active player, with perfect play of both players.
+ * The number is the distance to the end of the game in moves (plies). The winning player
+ * takes the shortest way, the losing player the longest.
+ */
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum GameValue {
+    Win(u32),
+    Loss(u32),
+    Draw(u32),
+}
+
+impl GameValue {
+    /// The value as seen by the other player
+    pub fn negate(self) -> GameValue {
+        match self {
+            GameValue::Win(d) => GameValue::Loss(d),
+            GameValue::Loss(d) => GameValue::Win(d),
+            GameValue::Draw(d) => GameValue::Draw(d),
+        }
+    }
+
+    pub fn distance(self) -> u32 {
+        match self {
+            GameValue::Win(d) | GameValue::Loss(d) | GameValue::Draw(d) => d,
+        }
+    }
+
+    /// Orders values by how good they are: fast wins first, slow losses last.
+    pub fn rank(self) -> i64 {
+        match self {
+            GameValue::Win(d) => i64::from(u32::MAX) - i64::from(d),
+            GameValue::Draw(_) => 0,
+            GameValue::Loss(d) => i64::from(d) - i64::from(u32::MAX),
+        }
+    }
+
+    /// The value one move earlier, for the player who made the move.
+    pub fn one_move_earlier(self, switched_player: bool) -> GameValue {
+        let value = if switched_player { self.negate() } else { self };
+        match value {
+            GameValue::Win(d) => GameValue::Win(d + 1),
+            GameValue::Loss(d) => GameValue::Loss(d + 1),
+            GameValue::Draw(d) => GameValue::Draw(d + 1),
+        }
+    }
+}
+
+/// The value of the move for the player who made it, if it ends the game.
+fn terminal_value(outcome: MoveOutcome, moving_player: Player) -> Option<GameValue> {
+    match outcome {
+        MoveOutcome::PlayerWon(p) if p == moving_player => Some(GameValue::Win(1)),
+        MoveOutcome::PlayerWon(_) => Some(GameValue::Loss(1)),
+        MoveOutcome::Tie => Some(GameValue::Draw(1)),
+        MoveOutcome::SwitchPlayer(_) | MoveOutcome::ContinuePlayer(_) => None,
+    }
+}
+
+/** The lookup table of a solved game: the exact value of every reachable state.
+ * It can be used as a perfect player with [SolvedGame::best_move].
+ */
+pub struct SolvedGame<GS> {
+    index: HashMap<GS, usize>,
+    values: Vec<GameValue>,
+}
+
+impl<GS> SolvedGame<GS>
+where
+    GS: GameState + Clone + Hash + Eq,
+{
+    /// Number of solved states
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// The value of the state for its active player, `None` if it was not reachable.
+    pub fn value(&self, gs: &GS) -> Option<GameValue> {
+        self.index.get(gs).map(|i| self.values[*i])
+    }
+
+    /// All solved states with their values
+    pub fn iter(&self) -> impl Iterator<Item = (&GS, GameValue)> {
+        self.index.iter().map(|(gs, i)| (gs, self.values[*i]))
+    }
+
+    /// The value of the move for the player making it.
+    pub fn move_value(&self, gs: &mut GS, m: &GS::Move) -> Option<GameValue> {
+        let moving_player = gs.active_player();
+        let outcome = gs.apply_move(m);
+        let value = match outcome {
+            MoveOutcome::SwitchPlayer(_) => self.value(gs).map(|v| v.one_move_earlier(true)),
+            MoveOutcome::ContinuePlayer(_) => self.value(gs).map(|v| v.one_move_earlier(false)),
+            _ => terminal_value(outcome, moving_player),
+        };
+        gs.reverse_move(m);
+        value
+    }
+
+    /// A move reaching the value of the state, `None` if there is no move or the state is unknown.
+    pub fn best_move(&self, gs: &mut GS) -> Option<GS::Move> {
+        let mut best: Option<(i64, GS::Move)> = None;
+        let mut move_iterator = gs.move_iterator();
+        while let Some(m) = move_iterator.next(gs) {
+            if let Some(value) = self.move_value(gs, m) {
+                if best.as_ref().is_none_or(|(rank, _)| value.rank() > *rank) {
+                    best = Some((value.rank(), m.clone()));
+                }
+            }
+        }
+        best.map(|(_, m)| m)
+    }
+}
+
+/** Solves the game, starting at `initial`.
+ *
+ * States without moves, that are not the end of the game, count as [GameValue::Draw] with
+ * distance 0. So do states from which neither player can force an end of the game (this can
+ * only happen in games with cycles).
+ */
+pub fn solve<GS>(initial: &GS) -> SolvedGame<GS>
+where
+    GS: GameState + Clone + Hash + Eq,
+{
+    // Enumerate the states, remember the edges backwards
+    let mut index = HashMap::new();
+    let mut states = vec![initial.clone()];
+    index.insert(initial.clone(), 0);
+    // For every state: (parent, did the player switch)
+    let mut parents: Vec<Vec<(usize, bool)>> = vec![Vec::new()];
+    // For every state: the number of moves without a known value
+    let mut open_moves: Vec<usize> = Vec::new();
+    // The values found for moves, sorted by distance: (state, value for its active player)
+    let mut buckets: Vec<Vec<(usize, GameValue)>> = Vec::new();
+
+    let mut current = 0;
+    while current < states.len() {
+        let mut gs = states[current].clone();
+        let moving_player = gs.active_player();
+        let mut move_count = 0;
+        let mut move_iterator = gs.move_iterator();
+        while let Some(m) = move_iterator.next(&gs) {
+            move_count += 1;
+            let outcome = gs.apply_move(m);
+            match outcome {
+                MoveOutcome::SwitchPlayer(_) | MoveOutcome::ContinuePlayer(_) => {
+                    let switched = matches!(outcome, MoveOutcome::SwitchPlayer(_));
+                    let child = match index.get(&gs) {
+                        Some(child) => *child,
+                        None => {
+                            states.push(gs.clone());
+                            parents.push(Vec::new());
+                            index.insert(gs.clone(), states.len() - 1);
+                            states.len() - 1
+                        }
+                    };
+                    parents[child].push((current, switched));
+                }
+                _ => {
+                    let value = terminal_value(outcome, moving_player)
+                        .expect("Only terminal outcomes are left");
+                    push_value(&mut buckets, current, value);
+                }
+            }
+            gs.reverse_move(m);
+        }
+        open_moves.push(move_count);
+        current += 1;
+    }
+    drop(states);
+
+    // Retrograde analysis: resolve the values in order of their distance
+    let mut values: Vec<Option<GameValue>> = vec![None; open_moves.len()];
+    let mut best_values: Vec<Option<GameValue>> = vec![None; open_moves.len()];
+    let mut distance = 0;
+    while distance < buckets.len() {
+        let bucket = std::mem::take(&mut buckets[distance]);
+        // A state can resolve with a value shorter than the current distance (a draw found
+        // before a longer loss), its parents then have to go back to that distance
+        let mut next_distance = distance + 1;
+        for (state, value) in bucket {
+            if values[state].is_some() {
+                continue;
+            }
+            open_moves[state] -= 1;
+            if best_values[state].is_none_or(|best| value.rank() > best.rank()) {
+                best_values[state] = Some(value);
+            }
+            // A win is resolved immediately, everything else once all moves are known
+            if matches!(value, GameValue::Win(_)) || open_moves[state] == 0 {
+                let resolved = best_values[state].expect("A value was just set");
+                values[state] = Some(resolved);
+                for (parent, switched) in &parents[state] {
+                    let parent_value = resolved.one_move_earlier(*switched);
+                    next_distance = next_distance.min(parent_value.distance() as usize);
+                    push_value(&mut buckets, *parent, parent_value);
+                }
+            }
+        }
+        distance = next_distance;
+    }
+
+    SolvedGame {
+        index,
+        values: values
+            .into_iter()
+            .map(|v| v.unwrap_or(GameValue::Draw(0)))
+            .collect(),
+    }
+}
+
+fn push_value(buckets: &mut Vec<Vec<(usize, GameValue)>>, state: usize, value: GameValue) {
+    let distance = value.distance() as usize;
+    if buckets.len() <= distance {
+        buckets.resize_with(distance + 1, Vec::new);
+    }
+    buckets[distance].push((state, value));
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing::test_games::NimGameState;
+
+    // A game on a fixed graph: from the root R there is one move to A. A can tie at once or
+    // move to B, from where the opponent forces a loss of A's player three plies after A.
+    #[derive(Clone, PartialEq, Eq, Hash, Debug)]
+    struct GraphGameState {
+        node: u8,
+        history: Vec<u8>,
+        active_player: Player,
+    }
+
+    enum Edge {
+        To(u8),
+        Tie,
+        Lose,
+    }
+
+    const ROOT: u8 = 0;
+    const A: u8 = 1;
+    const B: u8 = 2;
+    const C: u8 = 3;
+
+    fn edges(node: u8) -> &'static [Edge] {
+        match node {
+            ROOT => &[Edge::To(A)],
+            A => &[Edge::Tie, Edge::To(B)],
+            B => &[Edge::To(C)],
+            _ => &[Edge::Lose],
+        }
+    }
+
+    struct GraphMoveIterator {
+        current_move: Option<usize>,
+    }
+
+    impl MoveIterator for GraphMoveIterator {
+        type Move = usize;
+        type GameState = GraphGameState;
+
+        fn next(&mut self, gs: &Self::GameState) -> Option<&Self::Move> {
+            let next = self.current_move.map_or(0, |m| m + 1);
+            self.current_move = Some(next);
+            if next < edges(gs.node).len() {
+                self.current_move.as_ref()
+            } else {
+                None
+            }
+        }
+    }
+
+    impl GameState for GraphGameState {
+        type Move = usize;
+        type MoveIterator = GraphMoveIterator;
+
+        fn active_player(&self) -> Player {
+            self.active_player
+        }
+
+        fn move_iterator(&self) -> Self::MoveIterator {
+            GraphMoveIterator {
+                current_move: None,
+            }
+        }
+
+        fn apply_move(&mut self, m: &Self::Move) -> MoveOutcome {
+            self.history.push(self.node);
+            match edges(self.node)[*m] {
+                Edge::To(node) => {
+                    self.node = node;
+                    self.active_player = self.active_player.other();
+                    MoveOutcome::SwitchPlayer(self.active_player)
+                }
+                Edge::Tie => MoveOutcome::Tie,
+                Edge::Lose => MoveOutcome::PlayerWon(self.active_player.other()),
+            }
+        }
+
+        fn reverse_move(&mut self, _m: &Self::Move) {
+            let previous = self.history.pop().expect("A move to reverse");
+            if previous != self.node {
+                self.node = previous;
+                self.active_player = self.active_player.other();
+            }
+        }
+    }
+
+    fn graph_state(node: u8, history: &[u8], active_player: Player) -> GraphGameState {
+        GraphGameState {
+            node,
+            history: history.to_vec(),
+            active_player,
+        }
+    }
+
+    #[test]
+    fn solve_nim() {
+        // Setup
+        let gs = NimGameState::new(10);
+
+        // Act
+        let solved = solve(&gs);
+
+        // Test
+        // In Nim taking 1 or 2, piles divisible by 3 are lost
+        // (every pile size for both players, except 10 for Player2 and 9 for Player1)
+        assert_eq!(solved.len(), 18);
+        for (state, value) in solved.iter() {
+            let stones = state.stones;
+            if stones % 3 == 0 {
+                assert_eq!(value, GameValue::Loss(2 * stones / 3));
+            } else {
+                assert_eq!(value, GameValue::Win(1 + 2 * (stones / 3)));
+            }
+        }
+        assert_eq!(solved.value(&gs), Some(GameValue::Win(7)));
+    }
+
+    #[test]
+    fn best_move_keeps_winning() {
+        // Setup
+        let mut gs = NimGameState::new(10);
+        let solved = solve(&gs);
+
+        // Act
+        let m = solved.best_move(&mut gs);
+
+        // Test
+        assert_eq!(m, Some(1));
+        assert_eq!(gs, NimGameState::new(10));
+    }
+
+    #[test]
+    fn values_of_moves() {
+        // Setup
+        let mut gs = NimGameState::new(2);
+        let solved = solve(&gs);
+
+        // Act & Test
+        assert_eq!(solved.move_value(&mut gs, &1), Some(GameValue::Loss(2)));
+        assert_eq!(solved.move_value(&mut gs, &2), Some(GameValue::Win(1)));
+        assert_eq!(solved.best_move(&mut gs), Some(2));
+    }
+
+    #[test]
+    fn draw_distance_of_late_resolved_state() {
+        // Setup
+        let gs = graph_state(ROOT, &[], Player::Player1);
+
+        // Act
+        let solved = solve(&gs);
+
+        // Test
+        let a = graph_state(A, &[ROOT], Player::Player2);
+        let b = graph_state(B, &[ROOT, A], Player::Player1);
+        assert_eq!(solved.value(&b), Some(GameValue::Win(2)));
+        // The tie is preferred over the loss in 3, but A is only resolved after the loss
+        assert_eq!(solved.value(&a), Some(GameValue::Draw(1)));
+        assert_eq!(solved.value(&gs), Some(GameValue::Draw(2)));
+    }
+}
@@ -0,0 +1,274 @@
+/*! Endgame tablebases: the exact values of positions from a [crate::solver], stored compactly
+ * and indexed by a position index the game provides.
+ *
+ * The file format is:
+ * - the magic bytes `TBGT` and a version byte (1)
+ * - the number of entries as u64, little endian
+ * - one u16 (little endian) per entry. The upper 2 bits are the kind of value
+ *   (0 = unknown, 1 = win, 2 = loss, 3 = draw), the lower 14 bits the distance to the end.
+ *
+ * [crate::negamax::negamax_with_tablebase] consults a tablebase, before searching a position.
+ *
+ * The examples index tic-tac-toe and connect-four on a 4x4 board. Chess is out of scope: its
+ * endgames would need an index per set of remaining pieces, and a retrograde solver that does
+ * not start from the initial position.
+ */
+use std::fs::File;
+use std::hash::Hash;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use crate::model::GameState;
+use crate::solver::{GameValue, SolvedGame};
+
+const MAGIC: &[u8; 4] = b"TBGT";
+const VERSION: u8 = 1;
+const MAX_DISTANCE: u32 = (1 << 14) - 1;
+
+/** Maps the positions of a game (or a part of them, like all endgames with few pieces) to a
+ * dense range of indices.
+ */
+pub trait PositionIndex: GameState {
+    /// The number of indices, all indices are smaller than this.
+    fn position_count() -> u64;
+    /// The index of the position, `None` if it is not covered by the index.
+    fn position_index(&self) -> Option<u64>;
+}
+
+/// Something that knows the exact value of (some) positions.
+pub trait TablebaseProbe<GS> {
+    /// The value of the position for its active player, `None` if unknown.
+    fn probe(&self, gs: &GS) -> Option<GameValue>;
+}
+
+/// Probing nothing, always unknown.
+impl<GS> TablebaseProbe<GS> for () {
+    fn probe(&self, _gs: &GS) -> Option<GameValue> {
+        None
+    }
+}
+
+pub struct Tablebase {
+    entries: Vec<u16>,
+}
+
+impl Tablebase {
+    /// A tablebase with `size` entries, all unknown.
+    pub fn new(size: u64) -> Tablebase {
+        Tablebase {
+            entries: vec![0; size as usize],
+        }
+    }
+
+    /** Stores all solved positions, that are covered by the position index. Values with a
+     * distance which does not fit into the format are left unknown.
+     */
+    pub fn from_solved<GS>(solved: &SolvedGame<GS>) -> Tablebase
+    where
+        GS: PositionIndex + Clone + Hash + Eq,
+    {
+        let mut tablebase = Tablebase::new(GS::position_count());
+        for (gs, value) in solved.iter() {
+            if let Some(index) = gs.position_index() {
+                tablebase.set(index, value);
+            }
+        }
+        tablebase
+    }
+
+    pub fn len(&self) -> u64 {
+        self.entries.len() as u64
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, index: u64) -> Option<GameValue> {
+        self.entries.get(index as usize).and_then(|e| decode(*e))
+    }
+
+    pub fn set(&mut self, index: u64, value: GameValue) {
+        self.entries[index as usize] = encode(value);
+    }
+
+    pub fn write<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        w.write_all(MAGIC)?;
+        w.write_all(&[VERSION])?;
+        w.write_all(&self.len().to_le_bytes())?;
+        for entry in &self.entries {
+            w.write_all(&entry.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    pub fn read<R: Read>(r: &mut R) -> io::Result<Tablebase> {
+        let mut header = [0u8; 5];
+        r.read_exact(&mut header)?;
+        if &header[0..4] != MAGIC || header[4] != VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Not a tablebase file of a supported version",
+            ));
+        }
+        let mut count = [0u8; 8];
+        r.read_exact(&mut count)?;
+        let invalid_count = || {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "The number of entries does not match the file",
+            )
+        };
+        // The count is not trusted: the entries are read as far as they exist, instead of
+        // allocating the announced size up front
+        let size = u64::from_le_bytes(count)
+            .checked_mul(2)
+            .ok_or_else(invalid_count)?;
+        let mut bytes = Vec::new();
+        r.take(size).read_to_end(&mut bytes)?;
+        if bytes.len() as u64 != size {
+            return Err(invalid_count());
+        }
+        Ok(Tablebase {
+            entries: bytes
+                .chunks_exact(2)
+                .map(|b| u16::from_le_bytes([b[0], b[1]]))
+                .collect(),
+        })
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write(&mut writer)?;
+        writer.flush()
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Tablebase> {
+        Tablebase::read(&mut BufReader::new(File::open(path)?))
+    }
+}
+
+impl<GS: PositionIndex> TablebaseProbe<GS> for Tablebase {
+    fn probe(&self, gs: &GS) -> Option<GameValue> {
+        gs.position_index().and_then(|index| self.get(index))
+    }
+}
+
+fn encode(value: GameValue) -> u16 {
+    if value.distance() > MAX_DISTANCE {
+        return 0;
+    }
+    let kind = match value {
+        GameValue::Win(_) => 1,
+        GameValue::Loss(_) => 2,
+        GameValue::Draw(_) => 3,
+    };
+    (kind << 14) | value.distance() as u16
+}
+
+fn decode(entry: u16) -> Option<GameValue> {
+    let distance = u32::from(entry) & MAX_DISTANCE;
+    match entry >> 14 {
+        1 => Some(GameValue::Win(distance)),
+        2 => Some(GameValue::Loss(distance)),
+        3 => Some(GameValue::Draw(distance)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::model::Player;
+    use crate::solver::solve;
+    use crate::testing::test_games::NimGameState;
+
+    impl PositionIndex for NimGameState {
+        fn position_count() -> u64 {
+            // Up to 31 stones, for both players
+            64
+        }
+
+        fn position_index(&self) -> Option<u64> {
+            if self.stones >= 32 {
+                return None;
+            }
+            let player = match self.active_player {
+                Player::Player1 => 0,
+                Player::Player2 => 1,
+            };
+            Some(u64::from(self.stones) * 2 + player)
+        }
+    }
+
+    #[test]
+    fn encode_decode_values() {
+        for value in [
+            GameValue::Win(0),
+            GameValue::Win(17),
+            GameValue::Loss(MAX_DISTANCE),
+            GameValue::Draw(3),
+        ] {
+            assert_eq!(decode(encode(value)), Some(value));
+        }
+        assert_eq!(decode(encode(GameValue::Win(MAX_DISTANCE + 1))), None);
+        assert_eq!(decode(0), None);
+    }
+
+    #[test]
+    fn build_from_solver_and_probe() {
+        // Setup
+        let gs = NimGameState::new(10);
+        let solved = solve(&gs);
+
+        // Act
+        let tablebase = Tablebase::from_solved(&solved);
+
+        // Test
+        assert_eq!(tablebase.len(), NimGameState::position_count());
+        for (state, value) in solved.iter() {
+            assert_eq!(tablebase.probe(state), Some(value));
+        }
+        // Not reachable from the initial state, so unknown
+        let mut unknown = NimGameState::new(10);
+        unknown.active_player = unknown.active_player.other();
+        assert_eq!(tablebase.probe(&unknown), None);
+    }
+
+    #[test]
+    fn write_and_read() {
+        // Setup
+        let tablebase = Tablebase::from_solved(&solve(&NimGameState::new(10)));
+        let mut bytes = Vec::new();
+
+        // Act
+        tablebase.write(&mut bytes).unwrap();
+        let read = Tablebase::read(&mut bytes.as_slice()).unwrap();
+
+        // Test
+        assert_eq!(bytes.len(), 5 + 8 + 2 * tablebase.len() as usize);
+        assert_eq!(read.entries, tablebase.entries);
+    }
+
+    #[test]
+    fn reject_invalid_file() {
+        let bytes = b"NOTATABLEBASE".to_vec();
+        assert!(Tablebase::read(&mut bytes.as_slice()).is_err());
+    }
+
+    #[test]
+    fn reject_wrong_entry_count() {
+        for count in [u64::MAX, u64::MAX / 2, 3] {
+            // Setup
+            let mut bytes = b"TBGT\x01".to_vec();
+            bytes.extend_from_slice(&count.to_le_bytes());
+            bytes.extend_from_slice(&[0, 0, 0, 0]);
+
+            // Act
+            let error = Tablebase::read(&mut bytes.as_slice()).err().unwrap();
+
+            // Test
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+}
@@ -0,0 +1,301 @@
+/*! Checks every GameState implementation should pass, on a single state.
+ *
+ * The [crate::game_state_conformance_tests] macro runs them on random playouts, but they can
+ * also be called directly on hand crafted states.
+ * In the returned violations, `moves` holds the moves (if any) that were applied to the given
+ * state when the check failed.
+ */
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use crate::model::{GameState, MoveIterator, MoveOutcome};
+use crate::testing::perft::{violation, InvariantViolation, ViolationKind};
+
+fn hash_of<T: Hash>(value: &T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn all_moves<GS: GameState>(gs: &GS) -> Vec<GS::Move> {
+    let mut moves = Vec::new();
+    let mut move_iterator = gs.move_iterator();
+    while let Some(m) = move_iterator.next(gs) {
+        moves.push(m.clone());
+    }
+    moves
+}
+
+/// Applying and reversing any move restores an equal state with the same hash.
+pub fn check_round_trip<GS>(gs: &mut GS) -> Result<(), InvariantViolation<GS::Move>>
+where
+    GS: GameState + Clone + PartialEq + Hash,
+{
+    let before = gs.clone();
+    let before_hash = hash_of(gs);
+    for m in all_moves(gs) {
+        gs.apply_move(&m);
+        gs.reverse_move(&m);
+        if *gs != before {
+            return Err(violation(ViolationKind::StateNotRestored, &[m]));
+        }
+        if hash_of(gs) != before_hash {
+            return Err(violation(ViolationKind::HashChanged, &[m]));
+        }
+    }
+    Ok(())
+}
+
+// All move sequences of the given length, the game only ends with the last move
+fn sequences<GS: GameState + Clone>(gs: &GS, length: usize) -> Vec<Vec<GS::Move>> {
+    if length == 0 {
+        return vec![Vec::new()];
+    }
+    let mut result = Vec::new();
+    for m in all_moves(gs) {
+        let mut next = gs.clone();
+        let tails = match next.apply_move(&m) {
+            MoveOutcome::PlayerWon(_) | MoveOutcome::Tie if length > 1 => Vec::new(),
+            MoveOutcome::PlayerWon(_) | MoveOutcome::Tie => vec![Vec::new()],
+            _ => sequences(&next, length - 1),
+        };
+        for tail in tails {
+            let mut sequence = vec![m.clone()];
+            sequence.extend(tail);
+            result.push(sequence);
+        }
+    }
+    result
+}
+
+// The state after the moves, None if one of them is not legal
+fn play<GS>(gs: &GS, moves: &[GS::Move]) -> Option<GS>
+where
+    GS: GameState + Clone,
+    GS::Move: PartialEq,
+{
+    let mut state = gs.clone();
+    for m in moves {
+        if !all_moves(&state).contains(m) {
+            return None;
+        }
+        state.apply_move(m);
+    }
+    Some(state)
+}
+
+/** Equal states have equal hashes, also if they are reached by different move orders.
+ *
+ * Every sequence of two or three moves is compared with the sequence, where its first and last
+ * move are swapped: `a, b` with `b, a` and `a, b, c` with `c, b, a`. In games with alternating
+ * players the three move sequences reorder the moves of one player, the two move sequences
+ * find transpositions in games like Nim, where the moves don't belong to a player. If both
+ * are legal and end in equal states, these transpositions must have the same hash.
+ * The number of sequences grows with the cube of the number of moves.
+ */
+pub fn check_hash_consistency<GS>(gs: &GS) -> Result<(), InvariantViolation<GS::Move>>
+where
+    GS: GameState + Clone + PartialEq + Hash,
+    GS::Move: PartialEq,
+{
+    for sequence in sequences(gs, 2).into_iter().chain(sequences(gs, 3)) {
+        let mut transposed = sequence.clone();
+        transposed.swap(0, sequence.len() - 1);
+        if transposed == sequence {
+            continue;
+        }
+        let state = play(gs, &sequence).expect("The sequence is legal");
+        if let Some(transposed_state) = play(gs, &transposed) {
+            if state == transposed_state && hash_of(&state) != hash_of(&transposed_state) {
+                return Err(violation(
+                    ViolationKind::TranspositionHashDiffers,
+                    &transposed,
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/** The state has moves exactly if the game has not ended. `last_outcome` is the outcome of the
+ * move leading to the state, `None` for the initial state.
+ */
+pub fn check_terminal_detection<GS>(
+    gs: &GS,
+    last_outcome: Option<MoveOutcome>,
+) -> Result<(), InvariantViolation<GS::Move>>
+where
+    GS: GameState,
+{
+    let has_moves = gs.move_iterator().next(gs).is_some();
+    match last_outcome {
+        Some(MoveOutcome::PlayerWon(_)) | Some(MoveOutcome::Tie) if has_moves => {
+            Err(violation(ViolationKind::MovesAfterTerminal, &[]))
+        }
+        None | Some(MoveOutcome::SwitchPlayer(_)) | Some(MoveOutcome::ContinuePlayer(_))
+            if !has_moves =>
+        {
+            Err(violation(ViolationKind::NoMovesBeforeTerminal, &[]))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Two move iterators on equal states return the same moves in the same order.
+pub fn check_move_iterator_determinism<GS>(gs: &GS) -> Result<(), InvariantViolation<GS::Move>>
+where
+    GS: GameState + Clone,
+    GS::Move: PartialEq,
+{
+    let moves = all_moves(gs);
+    if moves != all_moves(gs) || moves != all_moves(&gs.clone()) {
+        return Err(violation(ViolationKind::NonDeterministicMoves, &[]));
+    }
+    Ok(())
+}
+
+/** Stamps out a module with property based conformance tests for a GameState.
+ *
+ * The tests play random legal move sequences from the given initial state (at most
+ * `max_plies` moves, 100 by default) and run the checks of [crate::testing::conformance] on the
+ * reached states. The GameState has to implement `Clone + PartialEq + Hash + Debug`, its moves
+ * `PartialEq + Debug`. The tests only exist with the `proptest` feature, they run with
+ * `cargo test --features proptest` (the tic-tac-toe example uses them this way).
+ *
+ ```ignore
+ turn_based_games::game_state_conformance_tests!(nim_conformance, NimGameState::new(10));
+ ```
+ */
+#[cfg(feature = "proptest")]
+#[macro_export]
+macro_rules! game_state_conformance_tests {
+    ($name:ident, $initial:expr) => {
+        $crate::game_state_conformance_tests!($name, $initial, 100);
+    };
+    ($name:ident, $initial:expr, $max_plies:expr) => {
+        mod $name {
+            #[allow(unused_imports)]
+            use super::*;
+            use $crate::testing::conformance;
+            use $crate::testing::proptest::test_runner::TestCaseError;
+            use $crate::testing::strategies::playout;
+
+            fn check<M: ::std::fmt::Debug>(
+                result: Result<(), $crate::testing::InvariantViolation<M>>,
+            ) -> Result<(), TestCaseError> {
+                result.map_err(|e| TestCaseError::fail(e.to_string()))
+            }
+
+            $crate::testing::proptest::proptest! {
+                #[test]
+                fn apply_reverse_round_trip(p in playout($initial, $max_plies)) {
+                    let mut state = p.state;
+                    check(conformance::check_round_trip(&mut state))?;
+                }
+
+                #[test]
+                fn hash_consistency(p in playout($initial, $max_plies)) {
+                    check(conformance::check_hash_consistency(&p.state))?;
+                }
+
+                #[test]
+                fn terminal_detection(p in playout($initial, $max_plies)) {
+                    check(conformance::check_terminal_detection(&p.state, p.last_outcome))?;
+                }
+
+                #[test]
+                fn move_iterator_determinism(p in playout($initial, $max_plies)) {
+                    check(conformance::check_move_iterator_determinism(&p.state))?;
+                }
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::model::Player;
+    use crate::testing::test_games::{NimGameState, NimMoveIterator};
+
+    // Nim, that remembers the last move in its hash, but not in its equality
+    #[derive(Clone, Debug)]
+    struct LastMoveNim {
+        nim: NimGameState,
+        last_move: u32,
+    }
+
+    impl PartialEq for LastMoveNim {
+        fn eq(&self, other: &Self) -> bool {
+            self.nim == other.nim
+        }
+    }
+
+    impl Hash for LastMoveNim {
+        fn hash<H: Hasher>(&self, state: &mut H) {
+            self.nim.hash(state);
+            self.last_move.hash(state);
+        }
+    }
+
+    struct LastMoveNimIterator(NimMoveIterator);
+
+    impl MoveIterator for LastMoveNimIterator {
+        type Move = u32;
+        type GameState = LastMoveNim;
+
+        fn next(&mut self, gs: &LastMoveNim) -> Option<&u32> {
+            self.0.next(&gs.nim)
+        }
+    }
+
+    impl GameState for LastMoveNim {
+        type Move = u32;
+        type MoveIterator = LastMoveNimIterator;
+
+        fn active_player(&self) -> Player {
+            self.nim.active_player()
+        }
+
+        fn move_iterator(&self) -> LastMoveNimIterator {
+            LastMoveNimIterator(self.nim.move_iterator())
+        }
+
+        fn apply_move(&mut self, m: &u32) -> MoveOutcome {
+            self.last_move = *m;
+            self.nim.apply_move(m)
+        }
+
+        fn reverse_move(&mut self, m: &u32) {
+            self.nim.reverse_move(m)
+        }
+    }
+
+    #[test]
+    fn transpositions_have_equal_hashes() {
+        assert!(check_hash_consistency(&NimGameState::new(10)).is_ok());
+        assert!(check_hash_consistency(&NimGameState::new(2)).is_ok());
+    }
+
+    #[test]
+    fn find_transpositions_with_different_hashes() {
+        // Setup
+        let gs = LastMoveNim {
+            nim: NimGameState::new(10),
+            last_move: 0,
+        };
+
+        // Act
+        let result = check_hash_consistency(&gs);
+
+        // Test
+        // Taking 1 and 2 stones leaves 7, in any order
+        assert_eq!(
+            result,
+            Err(InvariantViolation {
+                kind: ViolationKind::TranspositionHashDiffers,
+                moves: vec![2, 1],
+            })
+        );
+    }
+}
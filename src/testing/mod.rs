@@ -0,0 +1,16 @@
+/*! Tools for testing implementations of the [crate::model] traits.
+ *
+ * The functions in here walk the game tree of a [crate::model::GameState] and check that
+ * the implementation keeps the promises the rest of the crate relies on.
+ * With the `proptest` feature, [strategies] generates random games and
+ * [crate::game_state_conformance_tests] stamps out a property based test suite.
+ */
+pub mod conformance;
+pub mod perft;
+pub use perft::{check_invariants, perft, InvariantViolation, ViolationKind};
+#[cfg(feature = "proptest")]
+pub mod strategies;
+#[cfg(feature = "proptest")]
+pub use proptest;
+#[cfg(test)]
+pub(crate) mod test_games;
@@ -0,0 +1,271 @@
+use std::fmt;
+
+use crate::model::{GameState, MoveIterator, MoveOutcome, Player};
+
+/** Counts the nodes of the game tree at exactly the given depth
+ * (https://www.chessprogramming.org/Perft).
+ *
+ * Moves that end the game are leaves, the game tree does not continue below them. So they are
+ * only counted if they are made at the last level.
+ * Comparing the result with known values is a quick way to find bugs in the move generation.
+ */
+pub fn perft<GS: GameState>(gs: &mut GS, depth: i8) -> u64 {
+    if depth <= 0 {
+        return 1;
+    }
+    let mut nodes = 0;
+    let mut move_iterator = gs.move_iterator();
+    while let Some(m) = move_iterator.next(gs) {
+        nodes += match gs.apply_move(m) {
+            MoveOutcome::PlayerWon(_) | MoveOutcome::Tie => {
+                if depth == 1 {
+                    1
+                } else {
+                    0
+                }
+            }
+            MoveOutcome::SwitchPlayer(_) | MoveOutcome::ContinuePlayer(_) => perft(gs, depth - 1),
+        };
+        gs.reverse_move(m);
+    }
+    nodes
+}
+
+/// The different ways a GameState implementation can break the model promises.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ViolationKind {
+    /// `reverse_move` did not restore the state from before `apply_move`
+    StateNotRestored,
+    /// The player in the `MoveOutcome` does not follow from the player who made the move
+    WrongOutcomePlayer { expected: Player, announced: Player },
+    /// `active_player` does not return the player announced in the `MoveOutcome`
+    WrongActivePlayer { announced: Player, actual: Player },
+    /// The move iterator still returns moves after the game has ended
+    MovesAfterTerminal,
+    /// The move iterator returns no moves, but the game has not ended
+    NoMovesBeforeTerminal,
+    /// The hash of the state changed after `apply_move` and `reverse_move`
+    HashChanged,
+    /// Equal states, reached by different move orders, have different hashes
+    TranspositionHashDiffers,
+    /// Two move iterators on equal states returned different moves
+    NonDeterministicMoves,
+}
+
+/// A broken invariant, together with the moves leading to the offending state.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvariantViolation<M> {
+    pub kind: ViolationKind,
+    pub moves: Vec<M>,
+}
+
+impl<M: fmt::Debug> fmt::Display for InvariantViolation<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            ViolationKind::StateNotRestored => write!(f, "reverse_move did not restore the state")?,
+            ViolationKind::WrongOutcomePlayer {
+                expected,
+                announced,
+            } => write!(
+                f,
+                "the outcome announced {:?} as next player, expected {:?}",
+                announced, expected
+            )?,
+            ViolationKind::WrongActivePlayer { announced, actual } => write!(
+                f,
+                "active player is {:?}, but the outcome announced {:?}",
+                actual, announced
+            )?,
+            ViolationKind::MovesAfterTerminal => {
+                write!(f, "moves are generated after the game ended")?
+            }
+            ViolationKind::NoMovesBeforeTerminal => {
+                write!(f, "no moves are generated, but the game did not end")?
+            }
+            ViolationKind::HashChanged => {
+                write!(f, "the hash changed after apply_move and reverse_move")?
+            }
+            ViolationKind::TranspositionHashDiffers => {
+                write!(f, "equal states reached by different move orders have different hashes")?
+            }
+            ViolationKind::NonDeterministicMoves => {
+                write!(f, "move iterators on equal states returned different moves")?
+            }
+        }
+        write!(f, " (after moves {:?})", self.moves)
+    }
+}
+
+/** Walks the game tree up to the given depth and checks that the implementation of the
+ * GameState is consistent:
+ *
+ * - `reverse_move` restores exactly the state from before `apply_move`
+ * - after `SwitchPlayer(p)` the other player is active and `p` is that player
+ * - after `ContinuePlayer(p)` the same player is active and `p` is that player
+ * - after `PlayerWon` or `Tie` the move iterator does not return any move
+ *
+ * Returns the number of visited nodes, or the first violation found. Either way the state is
+ * the given one again afterwards: after a violation it is restored from a copy, as
+ * `reverse_move` might be the broken part.
+ */
+pub fn check_invariants<GS>(gs: &mut GS, depth: i8) -> Result<u64, InvariantViolation<GS::Move>>
+where
+    GS: GameState + Clone + PartialEq,
+{
+    let initial = gs.clone();
+    let mut moves = Vec::new();
+    let result = check_invariants_impl(gs, depth, &mut moves);
+    if result.is_err() {
+        *gs = initial;
+    }
+    result
+}
+
+fn check_invariants_impl<GS>(
+    gs: &mut GS,
+    depth: i8,
+    moves: &mut Vec<GS::Move>,
+) -> Result<u64, InvariantViolation<GS::Move>>
+where
+    GS: GameState + Clone + PartialEq,
+{
+    let mut nodes = 1;
+    if depth <= 0 {
+        return Ok(nodes);
+    }
+    let before = gs.clone();
+    let moving_player = gs.active_player();
+
+    let mut move_iterator = gs.move_iterator();
+    while let Some(m) = move_iterator.next(gs) {
+        moves.push(m.clone());
+        match gs.apply_move(m) {
+            MoveOutcome::PlayerWon(_) | MoveOutcome::Tie => {
+                if gs.move_iterator().next(gs).is_some() {
+                    return Err(violation(ViolationKind::MovesAfterTerminal, moves));
+                }
+                nodes += 1;
+            }
+            MoveOutcome::SwitchPlayer(p) => {
+                check_next_player(gs, moving_player.other(), p, moves)?;
+                nodes += check_invariants_impl(gs, depth - 1, moves)?;
+            }
+            MoveOutcome::ContinuePlayer(p) => {
+                check_next_player(gs, moving_player, p, moves)?;
+                nodes += check_invariants_impl(gs, depth - 1, moves)?;
+            }
+        };
+        gs.reverse_move(m);
+        if *gs != before {
+            return Err(violation(ViolationKind::StateNotRestored, moves));
+        }
+        moves.pop();
+    }
+    Ok(nodes)
+}
+
+fn check_next_player<GS: GameState>(
+    gs: &GS,
+    expected: Player,
+    announced: Player,
+    moves: &[GS::Move],
+) -> Result<(), InvariantViolation<GS::Move>> {
+    if announced != expected {
+        return Err(violation(
+            ViolationKind::WrongOutcomePlayer {
+                expected,
+                announced,
+            },
+            moves,
+        ));
+    }
+    let actual = gs.active_player();
+    if actual != announced {
+        return Err(violation(
+            ViolationKind::WrongActivePlayer { announced, actual },
+            moves,
+        ));
+    }
+    Ok(())
+}
+
+pub(crate) fn violation<M: Clone>(kind: ViolationKind, moves: &[M]) -> InvariantViolation<M> {
+    InvariantViolation {
+        kind,
+        moves: moves.to_vec(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing::test_games::NimGameState;
+
+    #[test]
+    fn perft_counts_nodes_at_depth() {
+        // Setup
+        let mut gs = NimGameState::new(5);
+
+        // Act & Test
+        assert_eq!(perft(&mut gs, 0), 1);
+        assert_eq!(perft(&mut gs, 1), 2);
+        assert_eq!(perft(&mut gs, 3), 7);
+        assert_eq!(perft(&mut gs, 5), 1);
+        assert_eq!(perft(&mut gs, 6), 0);
+        assert_eq!(gs, NimGameState::new(5));
+    }
+
+    #[test]
+    fn correct_game_has_no_violations() {
+        // Setup
+        let mut gs = NimGameState::new(5);
+
+        // Act
+        let result = check_invariants(&mut gs, 10);
+
+        // Test
+        assert!(result.is_ok());
+        assert_eq!(gs, NimGameState::new(5));
+    }
+
+    #[test]
+    fn detect_state_not_restored() {
+        // Setup
+        let mut gs = NimGameState::new(5);
+        gs.broken_reverse = true;
+
+        // Act
+        let result = check_invariants(&mut gs, 10);
+
+        // Test
+        assert_eq!(
+            result,
+            Err(InvariantViolation {
+                kind: ViolationKind::StateNotRestored,
+                moves: vec![1, 1, 1, 1]
+            })
+        );
+        let mut restored = NimGameState::new(5);
+        restored.broken_reverse = true;
+        assert_eq!(gs, restored);
+    }
+
+    #[test]
+    fn detect_wrong_outcome_player() {
+        // Setup
+        let mut gs = NimGameState::new(5);
+        gs.broken_outcome = true;
+
+        // Act
+        let result = check_invariants(&mut gs, 10);
+
+        // Test
+        assert_eq!(
+            result.unwrap_err().kind,
+            ViolationKind::WrongOutcomePlayer {
+                expected: Player::Player2,
+                announced: Player::Player1
+            }
+        );
+    }
+}
@@ -0,0 +1,115 @@
+/*! [proptest] strategies generating random legal games for any GameState.
+ *
+ * The random choices are drawn as indices into the list of available moves, so shrinking a
+ * failing case leads to shorter games and to moves earlier in the move order.
+ */
+use std::fmt;
+
+use proptest::prelude::*;
+use proptest::sample::Index;
+
+use crate::model::{GameState, MoveIterator, MoveOutcome};
+
+/// A game played from an initial state, stopped at the end of the game or after the max plies.
+pub struct Playout<GS: GameState> {
+    /// The state after all moves
+    pub state: GS,
+    /// The moves, in the order they were played
+    pub moves: Vec<GS::Move>,
+    /// The outcome of the last move, `None` if no move was played
+    pub last_outcome: Option<MoveOutcome>,
+}
+
+impl<GS> fmt::Debug for Playout<GS>
+where
+    GS: GameState + fmt::Debug,
+    GS::Move: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Playout")
+            .field("state", &self.state)
+            .field("moves", &self.moves)
+            .field("last_outcome", &self.last_outcome)
+            .finish()
+    }
+}
+
+/// Plays the game by picking the move at the given index in every position.
+pub fn play<GS: GameState>(mut gs: GS, choices: &[Index]) -> Playout<GS> {
+    let mut moves = Vec::new();
+    let mut last_outcome = None;
+    for choice in choices {
+        let mut available = Vec::new();
+        let mut move_iterator = gs.move_iterator();
+        while let Some(m) = move_iterator.next(&gs) {
+            available.push(m.clone());
+        }
+        if available.is_empty() {
+            break;
+        }
+        let m = choice.get(&available).clone();
+        let outcome = gs.apply_move(&m);
+        moves.push(m);
+        last_outcome = Some(outcome);
+        if let MoveOutcome::PlayerWon(_) | MoveOutcome::Tie = outcome {
+            break;
+        }
+    }
+    Playout {
+        state: gs,
+        moves,
+        last_outcome,
+    }
+}
+
+/// Random games of at most `max_plies` moves, starting at `initial`.
+pub fn playout<GS>(initial: GS, max_plies: usize) -> impl Strategy<Value = Playout<GS>>
+where
+    GS: GameState + Clone + fmt::Debug,
+    GS::Move: fmt::Debug,
+{
+    proptest::collection::vec(any::<Index>(), 0..=max_plies)
+        .prop_map(move |choices| play(initial.clone(), &choices))
+}
+
+/// Random legal move sequences of at most `max_plies` moves, starting at `initial`.
+pub fn move_sequence<GS>(initial: GS, max_plies: usize) -> impl Strategy<Value = Vec<GS::Move>>
+where
+    GS: GameState + Clone + fmt::Debug,
+    GS::Move: fmt::Debug,
+{
+    playout(initial, max_plies).prop_map(|p| p.moves)
+}
+
+/// Random states reachable from `initial` in at most `max_plies` moves.
+pub fn game_state<GS>(initial: GS, max_plies: usize) -> impl Strategy<Value = GS>
+where
+    GS: GameState + Clone + fmt::Debug,
+    GS::Move: fmt::Debug,
+{
+    playout(initial, max_plies).prop_map(|p| p.state)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing::test_games::NimGameState;
+
+    crate::game_state_conformance_tests!(nim_conformance, NimGameState::new(10));
+
+    proptest! {
+        #[test]
+        fn playouts_are_legal(moves in move_sequence(NimGameState::new(10), 20)) {
+            prop_assert!(moves.iter().all(|m| *m == 1 || *m == 2));
+            prop_assert!(moves.iter().sum::<u32>() <= 10);
+        }
+
+        #[test]
+        fn playouts_stop_at_end(p in playout(NimGameState::new(3), 20)) {
+            if p.state.stones == 0 {
+                prop_assert!(matches!(p.last_outcome, Some(MoveOutcome::PlayerWon(_))));
+            }
+            prop_assert!(p.moves.len() <= 3);
+        }
+    }
+}
@@ -0,0 +1,154 @@
+//! Small games used by the tests of the crate.
+use crate::model::{GameState, MiniMaxGameState, MoveIterator, MoveOutcome, Player};
+
+// Nim with a single pile: take 1 or 2 stones, who takes the last stone wins.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct NimGameState {
+    pub stones: u32,
+    pub active_player: Player,
+    pub broken_reverse: bool,
+    pub broken_outcome: bool,
+}
+
+impl NimGameState {
+    pub fn new(stones: u32) -> NimGameState {
+        NimGameState {
+            stones,
+            active_player: Player::Player1,
+            broken_reverse: false,
+            broken_outcome: false,
+        }
+    }
+}
+
+pub struct NimMoveIterator {
+    current_move: u32,
+}
+
+impl MoveIterator for NimMoveIterator {
+    type Move = u32;
+    type GameState = NimGameState;
+
+    fn next(&mut self, gs: &Self::GameState) -> Option<&Self::Move> {
+        self.current_move += 1;
+        if self.current_move > 2 || self.current_move > gs.stones {
+            None
+        } else {
+            Some(&self.current_move)
+        }
+    }
+}
+
+impl GameState for NimGameState {
+    type Move = u32;
+    type MoveIterator = NimMoveIterator;
+
+    fn active_player(&self) -> Player {
+        self.active_player
+    }
+
+    fn move_iterator(&self) -> Self::MoveIterator {
+        NimMoveIterator { current_move: 0 }
+    }
+
+    fn apply_move(&mut self, m: &Self::Move) -> MoveOutcome {
+        self.stones -= m;
+        if self.stones == 0 {
+            return MoveOutcome::PlayerWon(self.active_player);
+        }
+        if self.broken_outcome {
+            return MoveOutcome::SwitchPlayer(self.active_player);
+        }
+        self.active_player = self.active_player.other();
+        MoveOutcome::SwitchPlayer(self.active_player)
+    }
+
+    fn reverse_move(&mut self, m: &Self::Move) {
+        self.stones += m;
+        if self.stones != *m && !self.broken_reverse {
+            self.active_player = self.active_player.other();
+        }
+    }
+}
+
+impl MiniMaxGameState for NimGameState {
+    fn evaluate(&self, _player: &Player) -> i64 {
+        0
+    }
+}
+
+// A game without rules: every position has the same number of moves and the evaluation is a
+// pseudo random number derived from the moves. Good for comparing search algorithms.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct TreeGameState {
+    pub branching: u32,
+    pub moves: Vec<u32>,
+    pub active_player: Player,
+}
+
+impl TreeGameState {
+    pub fn new(branching: u32) -> TreeGameState {
+        TreeGameState {
+            branching,
+            moves: Vec::new(),
+            active_player: Player::Player1,
+        }
+    }
+}
+
+pub struct TreeMoveIterator {
+    current_move: Option<u32>,
+}
+
+impl MoveIterator for TreeMoveIterator {
+    type Move = u32;
+    type GameState = TreeGameState;
+
+    fn next(&mut self, gs: &Self::GameState) -> Option<&Self::Move> {
+        let next = self.current_move.map_or(0, |m| m + 1);
+        self.current_move = Some(next);
+        if next < gs.branching {
+            self.current_move.as_ref()
+        } else {
+            None
+        }
+    }
+}
+
+impl GameState for TreeGameState {
+    type Move = u32;
+    type MoveIterator = TreeMoveIterator;
+
+    fn active_player(&self) -> Player {
+        self.active_player
+    }
+
+    fn move_iterator(&self) -> Self::MoveIterator {
+        TreeMoveIterator { current_move: None }
+    }
+
+    fn apply_move(&mut self, m: &Self::Move) -> MoveOutcome {
+        self.moves.push(*m);
+        self.active_player = self.active_player.other();
+        MoveOutcome::SwitchPlayer(self.active_player)
+    }
+
+    fn reverse_move(&mut self, _m: &Self::Move) {
+        self.moves.pop();
+        self.active_player = self.active_player.other();
+    }
+}
+
+impl MiniMaxGameState for TreeGameState {
+    fn evaluate(&self, player: &Player) -> i64 {
+        // FNV-1a over the moves
+        let hash = self.moves.iter().fold(0xcbf2_9ce4_8422_2325u64, |h, m| {
+            (h ^ u64::from(*m)).wrapping_mul(0x0100_0000_01b3)
+        });
+        let p1_score = (hash % 201) as i64 - 100;
+        match player {
+            Player::Player1 => p1_score,
+            Player::Player2 => -p1_score,
+        }
+    }
+}
@@ -0,0 +1,357 @@
+// Bitboards: one bit per field, for example one bitboard per piece type. Neighbors, lines and
+// counts become a few bit operations instead of loops over a grid_board::Board.
+//
+// The fields are stored row by row, with an always empty padding column at the end of each
+// row, so shifting to the left or right can not wrap from one row into the next. A board with
+// `rows` and `cols` needs rows * (cols + 1) bits, so u64 is enough for connect-four (6x7) and
+// u128 for boards up to 10x10.
+use std::ops::{BitAnd, BitOr, Not, Shl, Shr};
+use crate::tools::grid_board::lines::LINE_DIRECTIONS;
+use crate::tools::grid_board::{Board, BoardPosition};
+
+// The integer types used for the bits of a BitBoard
+pub trait Bits:
+    Copy
+    + Eq
+    + BitAnd<Output = Self>
+    + BitOr<Output = Self>
+    + Not<Output = Self>
+    + Shl<usize, Output = Self>
+    + Shr<usize, Output = Self>
+{
+    const ZERO: Self;
+    const ONE: Self;
+    const SIZE: usize;
+
+    fn count_ones(self) -> u32;
+    fn trailing_zeros(self) -> u32;
+}
+
+impl Bits for u64 {
+    const ZERO: Self = 0;
+    const ONE: Self = 1;
+    const SIZE: usize = 64;
+
+    fn count_ones(self) -> u32 {
+        u64::count_ones(self)
+    }
+
+    fn trailing_zeros(self) -> u32 {
+        u64::trailing_zeros(self)
+    }
+}
+
+impl Bits for u128 {
+    const ZERO: Self = 0;
+    const ONE: Self = 1;
+    const SIZE: usize = 128;
+
+    fn count_ones(self) -> u32 {
+        u128::count_ones(self)
+    }
+
+    fn trailing_zeros(self) -> u32 {
+        u128::trailing_zeros(self)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct BitBoard<B> {
+    bits: B,
+    // All fields of the board set, without the padding column
+    mask: B,
+    rows: usize,
+    cols: usize,
+}
+
+pub type BitBoard64 = BitBoard<u64>;
+pub type BitBoard128 = BitBoard<u128>;
+
+impl<B: Bits> BitBoard<B> {
+    // An empty board, panics if the board does not fit into the bits
+    pub fn new(rows: usize, cols: usize) -> BitBoard<B> {
+        assert!(rows * (cols + 1) <= B::SIZE, "Board too large for the bitboard type");
+        let row = (0..cols).fold(B::ZERO, |row, x| row | (B::ONE << x));
+        let mask = (0..rows).fold(B::ZERO, |mask, y| mask | (row << (y * (cols + 1))));
+        BitBoard {
+            bits: B::ZERO,
+            mask,
+            rows,
+            cols,
+        }
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn columns(&self) -> usize {
+        self.cols
+    }
+
+    pub fn bits(&self) -> B {
+        self.bits
+    }
+
+    // The bit index of a position, None if it is not on the board
+    fn bit_index(&self, pos: &BoardPosition) -> Option<usize> {
+        pos.linear_index(self.rows, self.cols)
+            .map(|i| i % self.cols + i / self.cols * (self.cols + 1))
+    }
+
+    fn with_bits(&self, bits: B) -> BitBoard<B> {
+        BitBoard {
+            bits: bits & self.mask,
+            ..*self
+        }
+    }
+
+    // All fields set
+    pub fn full(&self) -> BitBoard<B> {
+        BitBoard {
+            bits: self.mask,
+            ..*self
+        }
+    }
+
+    pub fn get(&self, pos: &BoardPosition) -> bool {
+        self.bit_index(pos)
+            .map(|i| (self.bits >> i) & B::ONE == B::ONE)
+            .unwrap_or(false)
+    }
+
+    // Sets or clears the field, panics if the position is not on the board
+    pub fn set(&mut self, pos: &BoardPosition, value: bool) {
+        let index = self.bit_index(pos).expect("Invalid position");
+        if value {
+            self.bits = self.bits | (B::ONE << index);
+        } else {
+            self.bits = self.bits & !(B::ONE << index);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bits == B::ZERO
+    }
+
+    pub fn count(&self) -> u32 {
+        self.bits.count_ones()
+    }
+
+    pub fn union(&self, other: &BitBoard<B>) -> BitBoard<B> {
+        self.with_bits(self.bits | other.bits)
+    }
+
+    pub fn intersection(&self, other: &BitBoard<B>) -> BitBoard<B> {
+        self.with_bits(self.bits & other.bits)
+    }
+
+    pub fn complement(&self) -> BitBoard<B> {
+        self.with_bits(!self.bits)
+    }
+
+    // Moves every set field by (dx, dy), fields moved off the board are lost
+    pub fn shift(&self, dx: isize, dy: isize) -> BitBoard<B> {
+        let mut bits = self.bits;
+        // Single steps, so the padding column catches everything leaving a row
+        for _ in 0..dx.unsigned_abs() {
+            bits = if dx > 0 { bits << 1 } else { bits >> 1 };
+            bits = bits & self.mask;
+        }
+        let row_shift = dy.unsigned_abs() * (self.cols + 1);
+        if row_shift >= B::SIZE {
+            return self.with_bits(B::ZERO);
+        }
+        bits = if dy > 0 { bits << row_shift } else { bits >> row_shift };
+        self.with_bits(bits)
+    }
+
+    // All fields next to a set field (8 neighborhood), without the set fields themselves
+    pub fn neighbors(&self) -> BitBoard<B> {
+        let mut result = self.with_bits(B::ZERO);
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                if dx != 0 || dy != 0 {
+                    result = result.union(&self.shift(dx, dy));
+                }
+            }
+        }
+        result.intersection(&self.complement())
+    }
+
+    // The fields where a line of k set fields in direction (dx, dy) starts
+    pub fn line_starts(&self, k: usize, dx: isize, dy: isize) -> BitBoard<B> {
+        let mut starts = *self;
+        for step in 1..k as isize {
+            starts = starts.intersection(&self.shift(-dx * step, -dy * step));
+        }
+        starts
+    }
+
+    // Is there a line of k set fields in any direction?
+    pub fn has_line(&self, k: usize) -> bool {
+        LINE_DIRECTIONS
+            .iter()
+            .any(|(dx, dy)| !self.line_starts(k, *dx, *dy).is_empty())
+    }
+
+    // All set positions, row by row
+    pub fn positions(&self) -> Vec<BoardPosition> {
+        let mut bits = self.bits;
+        let mut positions = Vec::with_capacity(self.count() as usize);
+        while bits != B::ZERO {
+            let index = bits.trailing_zeros() as usize;
+            positions.push(BoardPosition::new(
+                (index % (self.cols + 1)) as isize,
+                (index / (self.cols + 1)) as isize,
+            ));
+            bits = bits & !(B::ONE << index);
+        }
+        positions
+    }
+
+    // The fields of the board, for which the predicate is true
+    pub fn from_board<FieldContent>(
+        board: &Board<FieldContent>,
+        predicate: impl Fn(&FieldContent) -> bool,
+    ) -> BitBoard<B> {
+        let mut result = BitBoard::new(board.rows(), board.columns());
+        for y in 0..board.rows() as isize {
+            for x in 0..board.columns() as isize {
+                let pos = BoardPosition::new(x, y);
+                if board.get(&pos).map(&predicate).unwrap_or(false) {
+                    result.set(&pos, true);
+                }
+            }
+        }
+        result
+    }
+
+    // Puts the content on all set fields of the board
+    pub fn write_to_board<FieldContent: Clone>(
+        &self,
+        board: &mut Board<FieldContent>,
+        content: &FieldContent,
+    ) {
+        for pos in self.positions() {
+            board.set(&pos, content.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn bitboard(rows: usize, cols: usize, positions: &[(isize, isize)]) -> BitBoard64 {
+        let mut board = BitBoard::new(rows, cols);
+        for (x, y) in positions {
+            board.set(&BoardPosition::new(*x, *y), true);
+        }
+        board
+    }
+
+    #[test]
+    fn set_and_get() {
+        // Setup
+        let mut board = BitBoard64::new(6, 7);
+
+        // Act
+        board.set(&BoardPosition::new(6, 5), true);
+        board.set(&BoardPosition::new(0, 1), true);
+        board.set(&BoardPosition::new(0, 1), false);
+
+        // Test
+        assert!(board.get(&BoardPosition::new(6, 5)));
+        assert!(!board.get(&BoardPosition::new(0, 1)));
+        assert!(!board.get(&BoardPosition::new(7, 5)));
+        assert_eq!(board.count(), 1);
+        assert_eq!(board.full().count(), 42);
+    }
+
+    #[test]
+    fn shift_does_not_wrap() {
+        // Setup
+        let board = bitboard(6, 7, &[(6, 0), (0, 5)]);
+
+        // Act
+        let right = board.shift(1, 0);
+        let left = board.shift(-1, 0);
+        let up = board.shift(0, 1);
+
+        // Test
+        // (6, 0) falls off the board instead of wrapping to (0, 1)
+        assert_eq!(right.count(), 1);
+        assert!(right.get(&BoardPosition::new(1, 5)));
+        assert_eq!(left.count(), 1);
+        assert!(left.get(&BoardPosition::new(5, 0)));
+        assert_eq!(up.count(), 1);
+        assert!(up.get(&BoardPosition::new(6, 1)));
+    }
+
+    #[test]
+    fn neighbors_in_corner() {
+        // Setup
+        let board = bitboard(3, 3, &[(0, 0)]);
+
+        // Act
+        let neighbors = board.neighbors();
+
+        // Test
+        assert_eq!(neighbors.count(), 3);
+        assert!(neighbors.get(&BoardPosition::new(1, 1)));
+        assert!(!neighbors.get(&BoardPosition::new(0, 0)));
+    }
+
+    #[test]
+    fn lines() {
+        // Horizontal, vertical and both diagonals
+        assert!(bitboard(6, 7, &[(2, 0), (3, 0), (4, 0), (5, 0)]).has_line(4));
+        assert!(bitboard(6, 7, &[(6, 2), (6, 3), (6, 4), (6, 5)]).has_line(4));
+        assert!(bitboard(6, 7, &[(0, 0), (1, 1), (2, 2), (3, 3)]).has_line(4));
+        assert!(bitboard(6, 7, &[(6, 0), (5, 1), (4, 2), (3, 3)]).has_line(4));
+
+        // Not across the end of a row
+        assert!(!bitboard(6, 7, &[(5, 0), (6, 0), (0, 1), (1, 1)]).has_line(4));
+        assert!(!bitboard(6, 7, &[(2, 0), (3, 0), (4, 0)]).has_line(4));
+
+        let starts = bitboard(3, 3, &[(0, 1), (1, 1), (2, 1)]).line_starts(2, 1, 0);
+        assert_eq!(starts.positions().len(), 2);
+    }
+
+    #[test]
+    fn large_board() {
+        // Setup
+        let mut board = BitBoard128::new(10, 10);
+
+        // Act
+        for i in 0..5 {
+            board.set(&BoardPosition::new(9 - i, i), true);
+        }
+
+        // Test
+        assert!(board.has_line(5));
+        assert!(!board.has_line(6));
+    }
+
+    #[test]
+    fn board_conversion() {
+        // Setup
+        let mut board = Board::new(3, 3, || ' ');
+        board.set(&BoardPosition::new(0, 0), 'X');
+        board.set(&BoardPosition::new(2, 1), 'X');
+        board.set(&BoardPosition::new(1, 1), 'O');
+
+        // Act
+        let xs = BitBoard64::from_board(&board, |f| *f == 'X');
+        let mut copy = Board::new(3, 3, || ' ');
+        xs.write_to_board(&mut copy, &'X');
+
+        // Test
+        assert_eq!(xs.count(), 2);
+        assert!(xs.get(&BoardPosition::new(2, 1)));
+        assert_eq!(copy.get(&BoardPosition::new(0, 0)), Some(&'X'));
+        assert_eq!(copy.get(&BoardPosition::new(1, 1)), Some(&' '));
+        assert_eq!(BitBoard64::from_board(&copy, |f| *f == 'X'), xs);
+    }
+}
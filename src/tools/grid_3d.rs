@@ -0,0 +1,359 @@
+// A three dimensional grid, for games like 3D tic-tac-toe (Qubic). Lines run in 13 directions:
+// the 3 axes, 6 diagonals within the planes and 4 space diagonals.
+
+// One direction (dx, dy, dz) of every line, the opposite directions are left out
+pub const LINE_DIRECTIONS_3D: [(isize, isize, isize); 13] = [
+    (1, 0, 0),
+    (0, 1, 0),
+    (0, 0, 1),
+    (1, 1, 0),
+    (1, -1, 0),
+    (1, 0, 1),
+    (1, 0, -1),
+    (0, 1, 1),
+    (0, 1, -1),
+    (1, 1, 1),
+    (1, 1, -1),
+    (1, -1, 1),
+    (1, -1, -1),
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Position3 {
+    x: isize,
+    y: isize,
+    z: isize,
+}
+
+impl Position3 {
+    pub fn new(x: isize, y: isize, z: isize) -> Position3 {
+        Position3 { x, y, z }
+    }
+
+    pub fn x(&self) -> isize {
+        self.x
+    }
+
+    pub fn y(&self) -> isize {
+        self.y
+    }
+
+    pub fn z(&self) -> isize {
+        self.z
+    }
+
+    pub fn step(&mut self, dx: isize, dy: isize, dz: isize) {
+        self.x += dx;
+        self.y += dy;
+        self.z += dz;
+    }
+}
+
+pub struct Board3<FieldContent> {
+    fields: Vec<FieldContent>,
+    cols: usize,
+    rows: usize,
+    layers: usize,
+}
+
+impl<FieldContent> Board3<FieldContent> {
+    pub fn new(
+        layers: usize,
+        rows: usize,
+        cols: usize,
+        default_content: fn() -> FieldContent,
+    ) -> Board3<FieldContent> {
+        Board3 {
+            fields: std::iter::repeat_with(default_content)
+                .take(layers * rows * cols)
+                .collect(),
+            cols,
+            rows,
+            layers,
+        }
+    }
+
+    pub fn columns(&self) -> usize {
+        self.cols
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn layers(&self) -> usize {
+        self.layers
+    }
+
+    fn index(&self, pos: &Position3) -> Option<usize> {
+        if pos.x < 0
+            || pos.y < 0
+            || pos.z < 0
+            || pos.x >= self.cols as isize
+            || pos.y >= self.rows as isize
+            || pos.z >= self.layers as isize
+        {
+            None
+        } else {
+            Some(pos.x as usize + (pos.y as usize + pos.z as usize * self.rows) * self.cols)
+        }
+    }
+
+    // Set the content of a field
+    pub fn set(&mut self, pos: &Position3, value: FieldContent) {
+        if let Some(index) = self.index(pos) {
+            self.fields[index] = value;
+        } else {
+            panic!("Invalid insert position");
+        }
+    }
+
+    // Get the content of the specific field
+    pub fn get(&self, pos: &Position3) -> Option<&FieldContent> {
+        self.index(pos).map(|i| &self.fields[i])
+    }
+
+    // Iterates from the position in the direction, until it leaves the board
+    pub fn field_iterator(
+        &self,
+        pos: Position3,
+        dx: isize,
+        dy: isize,
+        dz: isize,
+    ) -> Field3Iterator<'_, FieldContent> {
+        Field3Iterator {
+            pos,
+            dx,
+            dy,
+            dz,
+            board: self,
+        }
+    }
+
+    // Slides a window of window_size fields from the position in the direction, until the front
+    // of the window leaves the board
+    pub fn sliding_window_iterator(
+        &self,
+        pos: Position3,
+        dx: isize,
+        dy: isize,
+        dz: isize,
+        window_size: usize,
+    ) -> SlidingWindow3Iterator<'_, FieldContent> {
+        SlidingWindow3Iterator {
+            fields: self.field_iterator(pos, dx, dy, dz),
+            end_pos: pos,
+            current_length: 0,
+            full_length: window_size,
+        }
+    }
+
+    // All lines of k fields on the board, as start position and direction
+    pub fn lines(&self, k: usize) -> Vec<(Position3, (isize, isize, isize))> {
+        let mut lines = Vec::new();
+        let last = k as isize - 1;
+        for z in 0..self.layers as isize {
+            for y in 0..self.rows as isize {
+                for x in 0..self.cols as isize {
+                    let start = Position3::new(x, y, z);
+                    for (dx, dy, dz) in LINE_DIRECTIONS_3D {
+                        let end = Position3::new(x + dx * last, y + dy * last, z + dz * last);
+                        if k > 0 && self.index(&end).is_some() {
+                            lines.push((start, (dx, dy, dz)));
+                        }
+                    }
+                }
+            }
+        }
+        lines
+    }
+
+    // Number of fields in the line through the position in direction (dx, dy, dz), that have
+    // the same content as the position. 0 for empty fields, which don't make lines.
+    pub fn line_length(
+        &self,
+        pos: &Position3,
+        dx: isize,
+        dy: isize,
+        dz: isize,
+        is_empty: impl Fn(&FieldContent) -> bool,
+    ) -> usize
+    where
+        FieldContent: PartialEq,
+    {
+        let content = match self.get(pos) {
+            Some(content) if !is_empty(content) => content,
+            _ => return 0,
+        };
+        let mut length = 1;
+        for (sx, sy, sz) in [(dx, dy, dz), (-dx, -dy, -dz)] {
+            length += self
+                .field_iterator(*pos, sx, sy, sz)
+                .skip(1)
+                .take_while(|c| *c == content)
+                .count();
+        }
+        length
+    }
+
+    // Is the piece at the position part of a line of at least k equal pieces?
+    pub fn completes_line(
+        &self,
+        pos: &Position3,
+        k: usize,
+        is_empty: impl Fn(&FieldContent) -> bool,
+    ) -> bool
+    where
+        FieldContent: PartialEq,
+    {
+        LINE_DIRECTIONS_3D
+            .iter()
+            .any(|(dx, dy, dz)| self.line_length(pos, *dx, *dy, *dz, &is_empty) >= k)
+    }
+}
+
+pub struct Field3Iterator<'a, FieldContent> {
+    pos: Position3,
+    dx: isize,
+    dy: isize,
+    dz: isize,
+    board: &'a Board3<FieldContent>,
+}
+
+impl<'a, FieldContent> Iterator for Field3Iterator<'a, FieldContent> {
+    type Item = &'a FieldContent;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let result = self.board.get(&self.pos);
+        self.pos.step(self.dx, self.dy, self.dz);
+        result
+    }
+}
+
+pub struct SlidingWindow3Iterator<'a, FieldContent> {
+    fields: Field3Iterator<'a, FieldContent>,
+    end_pos: Position3,
+    current_length: usize,
+    full_length: usize,
+}
+
+impl<'a, FieldContent> Iterator for SlidingWindow3Iterator<'a, FieldContent> {
+    type Item = SlidingWindow3Step<'a, FieldContent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let front_pos = self.fields.pos;
+        let new_field = self.fields.next()?;
+        let removed_field = if self.current_length == self.full_length {
+            let removed_field = self.fields.board.get(&self.end_pos);
+            self.end_pos
+                .step(self.fields.dx, self.fields.dy, self.fields.dz);
+            removed_field
+        } else {
+            self.current_length += 1;
+            None
+        };
+        Some(SlidingWindow3Step {
+            new_field,
+            removed_field,
+            length: self.current_length,
+            is_full: self.current_length == self.full_length,
+            front_pos,
+            end_pos: self.end_pos,
+        })
+    }
+}
+
+pub struct SlidingWindow3Step<'a, FieldContent> {
+    // The field sliding into the window
+    pub new_field: &'a FieldContent,
+    // The field being removed from the window (if any)
+    pub removed_field: Option<&'a FieldContent>,
+    // The current length of the window
+    pub length: usize,
+    // Is the window full? (this is equal to length==window_size)
+    pub is_full: bool,
+    // The position of the new field
+    pub front_pos: Position3,
+    // The position of the oldest field still in the window
+    pub end_pos: Position3,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn is_empty(c: &char) -> bool {
+        *c == '.'
+    }
+
+    #[test]
+    fn number_of_lines() {
+        // Qubic has 76 winning lines, 3x3x3 tic-tac-toe 49
+        assert_eq!(Board3::new(4, 4, 4, || 0).lines(4).len(), 76);
+        assert_eq!(Board3::new(3, 3, 3, || 0).lines(3).len(), 49);
+    }
+
+    #[test]
+    fn field_iterator() {
+        // Setup
+        let mut board = Board3::new(4, 4, 4, || 0);
+        for i in 0..4 {
+            board.set(&Position3::new(i, 3 - i, i), i + 1);
+        }
+
+        // Act
+        let fields: Vec<&isize> = board
+            .field_iterator(Position3::new(0, 3, 0), 1, -1, 1)
+            .collect();
+
+        // Test
+        assert_eq!(fields, vec![&1, &2, &3, &4]);
+        assert_eq!(board.get(&Position3::new(0, 0, 4)), None);
+    }
+
+    #[test]
+    fn space_diagonal_wins() {
+        // Setup
+        let mut board = Board3::new(4, 4, 4, || '.');
+        for i in 0..4 {
+            board.set(&Position3::new(3 - i, i, 3 - i), 'X');
+        }
+
+        // Test
+        assert!(board.completes_line(&Position3::new(1, 2, 1), 4, is_empty));
+        assert!(!board.completes_line(&Position3::new(1, 2, 1), 5, is_empty));
+        assert_eq!(
+            board.line_length(&Position3::new(3, 0, 3), 1, 0, 0, is_empty),
+            1
+        );
+        // The empty fields around make no line
+        assert_eq!(
+            board.line_length(&Position3::new(0, 0, 0), 1, 0, 0, is_empty),
+            0
+        );
+        assert!(!board.completes_line(&Position3::new(0, 0, 0), 4, is_empty));
+    }
+
+    #[test]
+    fn sliding_window() {
+        // Setup
+        let mut board = Board3::new(4, 4, 4, || '.');
+        for z in [0, 1, 3] {
+            board.set(&Position3::new(2, 1, z), 'X');
+        }
+
+        // Act
+        let mut count = 0;
+        let mut counts = Vec::new();
+        for step in board.sliding_window_iterator(Position3::new(2, 1, 0), 0, 0, 1, 2) {
+            count += (*step.new_field == 'X') as usize;
+            count -= step.removed_field.is_some_and(|f| *f == 'X') as usize;
+            if step.is_full {
+                counts.push((step.end_pos.z(), count));
+            }
+        }
+
+        // Test
+        assert_eq!(counts, vec![(0, 2), (1, 1), (2, 1)]);
+    }
+}
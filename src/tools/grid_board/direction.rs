@@ -0,0 +1,234 @@
+use crate::tools::grid_board::position::BoardPosition;
+
+// A step (dx, dy) on the board. North is towards y = 0.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Direction {
+    pub dx: isize,
+    pub dy: isize,
+}
+
+impl Direction {
+    pub const NORTH: Direction = Direction::new(0, -1);
+    pub const NORTH_EAST: Direction = Direction::new(1, -1);
+    pub const EAST: Direction = Direction::new(1, 0);
+    pub const SOUTH_EAST: Direction = Direction::new(1, 1);
+    pub const SOUTH: Direction = Direction::new(0, 1);
+    pub const SOUTH_WEST: Direction = Direction::new(-1, 1);
+    pub const WEST: Direction = Direction::new(-1, 0);
+    pub const NORTH_WEST: Direction = Direction::new(-1, -1);
+
+    pub const ORTHOGONAL: [Direction; 4] = [
+        Direction::NORTH,
+        Direction::EAST,
+        Direction::SOUTH,
+        Direction::WEST,
+    ];
+
+    pub const DIAGONAL: [Direction; 4] = [
+        Direction::NORTH_EAST,
+        Direction::SOUTH_EAST,
+        Direction::SOUTH_WEST,
+        Direction::NORTH_WEST,
+    ];
+
+    // Orthogonal and diagonal, clockwise starting north
+    pub const ALL: [Direction; 8] = [
+        Direction::NORTH,
+        Direction::NORTH_EAST,
+        Direction::EAST,
+        Direction::SOUTH_EAST,
+        Direction::SOUTH,
+        Direction::SOUTH_WEST,
+        Direction::WEST,
+        Direction::NORTH_WEST,
+    ];
+
+    // The jumps of a chess knight
+    pub const KNIGHT: [Direction; 8] = [
+        Direction::new(1, -2),
+        Direction::new(2, -1),
+        Direction::new(2, 1),
+        Direction::new(1, 2),
+        Direction::new(-1, 2),
+        Direction::new(-2, 1),
+        Direction::new(-2, -1),
+        Direction::new(-1, -2),
+    ];
+
+    pub const fn new(dx: isize, dy: isize) -> Direction {
+        Direction { dx, dy }
+    }
+
+    pub fn opposite(&self) -> Direction {
+        Direction::new(-self.dx, -self.dy)
+    }
+
+    // The position one step in this direction
+    pub fn from(&self, pos: &BoardPosition) -> BoardPosition {
+        let mut result = *pos;
+        result.step(self.dx, self.dy);
+        result
+    }
+}
+
+// How the edges of a board are connected
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Topology {
+    // Leaving the board ends at the edge
+    #[default]
+    Flat,
+    // The left and the right edge are connected
+    Cylinder,
+    // Additionally the top and the bottom edge are connected
+    Torus,
+}
+
+impl Topology {
+    // The position on a board with the size, wrapped around connected edges, None if it is
+    // off the board
+    pub fn wrap(&self, pos: &BoardPosition, rows: usize, cols: usize) -> Option<BoardPosition> {
+        let (mut x, mut y) = (pos.x(), pos.y());
+        if *self != Topology::Flat {
+            x = x.rem_euclid(cols as isize);
+        }
+        if *self == Topology::Torus {
+            y = y.rem_euclid(rows as isize);
+        }
+        let result = BoardPosition::new(x, y);
+        result.linear_index(rows, cols).map(|_| result)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tools::grid_board::regions::Neighborhood;
+    use crate::tools::grid_board::Board;
+
+    #[test]
+    fn directions() {
+        assert_eq!(Direction::NORTH.opposite(), Direction::SOUTH);
+        assert_eq!(
+            Direction::SOUTH_WEST.from(&BoardPosition::new(3, 3)),
+            BoardPosition::new(2, 4)
+        );
+        for d in Direction::ALL {
+            assert_eq!(d.dx.abs().max(d.dy.abs()), 1);
+        }
+        for d in Direction::KNIGHT {
+            assert_eq!(d.dx.abs() + d.dy.abs(), 3);
+            assert!(Direction::KNIGHT.contains(&d.opposite()));
+        }
+    }
+
+    #[test]
+    fn wrap() {
+        // Setup
+        let pos = BoardPosition::new(-1, 5);
+
+        // Test
+        assert_eq!(Topology::Flat.wrap(&pos, 5, 4), None);
+        assert_eq!(Topology::Cylinder.wrap(&pos, 5, 4), None);
+        assert_eq!(
+            Topology::Torus.wrap(&pos, 5, 4),
+            Some(BoardPosition::new(3, 0))
+        );
+        assert_eq!(
+            Topology::Cylinder.wrap(&BoardPosition::new(4, 2), 5, 4),
+            Some(BoardPosition::new(0, 2))
+        );
+        assert_eq!(
+            Topology::Flat.wrap(&BoardPosition::new(3, 4), 5, 4),
+            Some(BoardPosition::new(3, 4))
+        );
+    }
+
+    #[test]
+    fn torus_board() {
+        // Setup
+        let mut board = Board::new(3, 4, || 0);
+        board.set_topology(Topology::Torus);
+
+        // Act
+        board.set(&BoardPosition::new(-1, -1), 7);
+
+        // Test
+        assert_eq!(board.get(&BoardPosition::new(3, 2)), Some(&7));
+        assert_eq!(
+            board
+                .neighbors(&BoardPosition::new(0, 0), Neighborhood::Eight)
+                .count(),
+            8
+        );
+        assert!(board
+            .neighbors(&BoardPosition::new(0, 0), Neighborhood::Four)
+            .any(|n| n == BoardPosition::new(0, 2)));
+    }
+
+    #[test]
+    fn iterators_stop_after_one_lap() {
+        // Setup
+        let mut board = Board::new(3, 4, || 0);
+        board.set_topology(Topology::Cylinder);
+        for x in 0..4 {
+            board.set(&BoardPosition::new(x, 1), x);
+        }
+
+        // Act
+        let row: Vec<&isize> = board
+            .direction_iterator(BoardPosition::new(2, 1), Direction::EAST)
+            .collect();
+        let column = board
+            .direction_iterator(BoardPosition::new(2, 1), Direction::SOUTH)
+            .count();
+        let windows: Vec<(BoardPosition, BoardPosition)> = board
+            .sliding_window_iterator(BoardPosition::new(0, 1), 1, 0, 3)
+            .filter(|step| step.is_full)
+            .map(|step| (step.end_pos, step.font_pos))
+            .collect();
+
+        // Test
+        assert_eq!(row, vec![&2, &3, &0, &1]);
+        // The top and bottom edge are not connected
+        assert_eq!(column, 2);
+        // One window starting at every field of the row
+        assert_eq!(windows.len(), 4);
+        assert_eq!(board.wrap(&windows[3].1), Some(BoardPosition::new(1, 1)));
+        assert_eq!(
+            board.line_length(&BoardPosition::new(1, 1), 1, 0, |c| *c == 0),
+            1
+        );
+    }
+
+    #[test]
+    fn lines_around_the_board() {
+        // Setup
+        let mut cylinder = Board::new(3, 4, || '.');
+        cylinder.set_topology(Topology::Cylinder);
+        let mut torus = Board::new(4, 4, || '.');
+        torus.set_topology(Topology::Torus);
+        for i in 0..4 {
+            cylinder.set(&BoardPosition::new(i, 1), 'X');
+            torus.set(&BoardPosition::new(i, 3 - i), 'X');
+        }
+        cylinder.set(&BoardPosition::new(1, 2), 'X');
+        let is_empty = |c: &char| *c == '.';
+
+        // Test
+        assert_eq!(
+            cylinder.line_length(&BoardPosition::new(2, 1), 1, 0, is_empty),
+            4
+        );
+        assert!(cylinder.completes_line(&BoardPosition::new(2, 1), 4, is_empty));
+        assert!(!cylinder.completes_line(&BoardPosition::new(2, 1), 5, is_empty));
+        assert_eq!(
+            cylinder.line_length(&BoardPosition::new(1, 1), 0, 1, is_empty),
+            2
+        );
+        assert_eq!(
+            torus.line_length(&BoardPosition::new(1, 2), 1, -1, is_empty),
+            4
+        );
+        assert!(!torus.completes_line(&BoardPosition::new(1, 2), 5, is_empty));
+    }
+}
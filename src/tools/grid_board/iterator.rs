@@ -3,6 +3,9 @@ use crate::tools::grid_board::position::BoardPosition;
 
 pub struct FieldIterator<'a, FieldContent> {
     pos: BoardPosition,
+    // Where a wrapping board is left again, after one lap
+    start: Option<BoardPosition>,
+    started: bool,
     col_dir: isize,
     row_dir: isize,
     field: &'a Board<FieldContent>
@@ -13,6 +16,8 @@ impl<'a, FieldContent> FieldIterator<'a, FieldContent> {
         -> Self {
         FieldIterator {
             pos: start_pos,
+            start: board.wrap(&start_pos),
+            started: false,
             col_dir,
             row_dir,
             field: board,
@@ -24,6 +29,10 @@ impl<'a, FieldContent> Iterator for FieldIterator<'a, FieldContent> {
     type Item = &'a FieldContent;
 
     fn next(&mut self) -> Option<Self::Item> {
+        if self.started && self.field.wrap(&self.pos) == self.start {
+            return None;
+        }
+        self.started = true;
         let result = self.field.get(&self.pos);
         self.pos.step(self.row_dir, self.col_dir);
         result
@@ -0,0 +1,268 @@
+use crate::tools::grid_board::{Board, BoardPosition};
+
+// The four directions (dx, dy) of lines: horizontal, vertical and both diagonals
+pub const LINE_DIRECTIONS: [(isize, isize); 4] = [(1, 0), (0, 1), (1, 1), (1, -1)];
+
+// k consecutive fields in a direction, with the number of pieces of each owner in it
+#[derive(Clone, Debug, PartialEq)]
+pub struct LineWindow<Owner> {
+    pub start: BoardPosition,
+    pub direction: (isize, isize),
+    pub counts: Vec<(Owner, usize)>,
+}
+
+impl<Owner: PartialEq> LineWindow<Owner> {
+    // Number of pieces of the owner in the window
+    pub fn count(&self, owner: &Owner) -> usize {
+        self.counts
+            .iter()
+            .find(|(o, _)| o == owner)
+            .map(|(_, count)| *count)
+            .unwrap_or(0)
+    }
+
+    // A window without pieces of more than one owner, that can still become a line
+    pub fn is_open(&self) -> bool {
+        self.counts.len() <= 1
+    }
+}
+
+impl<FieldContent> Board<FieldContent> {
+    // Number of fields in the line through the position in direction (dx, dy), that have the
+    // same content as the position. 0 for empty fields, which don't make lines.
+    pub fn line_length(
+        &self,
+        pos: &BoardPosition,
+        dx: isize,
+        dy: isize,
+        is_empty: impl Fn(&FieldContent) -> bool,
+    ) -> usize
+    where
+        FieldContent: PartialEq,
+    {
+        let content = match self.get(pos) {
+            Some(content) if !is_empty(content) => content,
+            _ => return 0,
+        };
+        let mut length = 1;
+        let start = self.wrap(pos);
+        let mut current = *pos;
+        loop {
+            current.step(dx, dy);
+            // On wrapping topologies the whole loop can be part of the line
+            if self.wrap(&current) == start {
+                return length;
+            }
+            match self.get(&current) {
+                Some(c) if c == content => length += 1,
+                _ => break,
+            }
+        }
+        // Without a full loop the backward scan ends at the latest where the forward scan did
+        length += self
+            .field_iterator(*pos, -dx, -dy)
+            .skip(1)
+            .take_while(|c| *c == content)
+            .count();
+        length
+    }
+
+    // Is the piece at the position part of a line of at least k equal pieces? Checking the
+    // position of the last move is enough to find a win in an m,n,k-game.
+    pub fn completes_line(
+        &self,
+        pos: &BoardPosition,
+        k: usize,
+        is_empty: impl Fn(&FieldContent) -> bool,
+    ) -> bool
+    where
+        FieldContent: PartialEq,
+    {
+        LINE_DIRECTIONS
+            .iter()
+            .any(|(dx, dy)| self.line_length(pos, *dx, *dy, &is_empty) >= k)
+    }
+
+    // The window of k fields from start in direction (dx, dy), None if it leaves the board
+    fn window<Owner: PartialEq>(
+        &self,
+        start: BoardPosition,
+        dx: isize,
+        dy: isize,
+        k: usize,
+        owner: &impl Fn(&FieldContent) -> Option<Owner>,
+    ) -> Option<LineWindow<Owner>> {
+        let mut counts: Vec<(Owner, usize)> = Vec::new();
+        let mut current = start;
+        for _ in 0..k {
+            if let Some(o) = owner(self.get(&current)?) {
+                match counts.iter_mut().find(|(c, _)| *c == o) {
+                    Some((_, count)) => *count += 1,
+                    None => counts.push((o, 1)),
+                }
+            }
+            current.step(dx, dy);
+        }
+        Some(LineWindow {
+            start,
+            direction: (dx, dy),
+            counts,
+        })
+    }
+
+    // All windows of k fields in the four line directions. The owner function tells, to whom
+    // the piece on a field belongs (None for empty fields).
+    pub fn windows<Owner: PartialEq>(
+        &self,
+        k: usize,
+        owner: impl Fn(&FieldContent) -> Option<Owner>,
+    ) -> Vec<LineWindow<Owner>> {
+        let mut windows = Vec::new();
+        for y in 0..self.rows() as isize {
+            for x in 0..self.columns() as isize {
+                for (dx, dy) in LINE_DIRECTIONS {
+                    windows.extend(self.window(BoardPosition::new(x, y), dx, dy, k, &owner));
+                }
+            }
+        }
+        windows
+    }
+
+    // All windows of k fields, that contain the position
+    pub fn windows_through<Owner: PartialEq>(
+        &self,
+        pos: &BoardPosition,
+        k: usize,
+        owner: impl Fn(&FieldContent) -> Option<Owner>,
+    ) -> Vec<LineWindow<Owner>> {
+        let mut windows = Vec::new();
+        for (dx, dy) in LINE_DIRECTIONS {
+            for offset in 0..k as isize {
+                let mut start = *pos;
+                start.step(-dx * offset, -dy * offset);
+                windows.extend(self.window(start, dx, dy, k, &owner));
+            }
+        }
+        windows
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Tic-tac-toe like boards from rows of 'X', 'O' and '.', the first row is y = 0
+    fn board(rows: &[&str]) -> Board<char> {
+        let mut board = Board::new(rows.len(), rows[0].len(), || '.');
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                board.set(&BoardPosition::new(x as isize, y as isize), c);
+            }
+        }
+        board
+    }
+
+    fn is_empty(c: &char) -> bool {
+        *c == '.'
+    }
+
+    fn owner(c: &char) -> Option<char> {
+        if *c == '.' {
+            None
+        } else {
+            Some(*c)
+        }
+    }
+
+    #[test]
+    fn line_length() {
+        // Setup
+        let board = board(&["XXO", "XOO", "XXO"]);
+
+        // Test
+        assert_eq!(
+            board.line_length(&BoardPosition::new(0, 1), 0, 1, is_empty),
+            3
+        );
+        assert_eq!(
+            board.line_length(&BoardPosition::new(0, 1), 1, 0, is_empty),
+            1
+        );
+        assert_eq!(
+            board.line_length(&BoardPosition::new(1, 0), 1, 0, is_empty),
+            2
+        );
+        assert_eq!(
+            board.line_length(&BoardPosition::new(2, 0), 1, -1, is_empty),
+            2
+        );
+        assert_eq!(
+            board.line_length(&BoardPosition::new(5, 0), 1, 0, is_empty),
+            0
+        );
+    }
+
+    #[test]
+    fn completes_line() {
+        // Setup
+        let board = board(&[".XO.", "XOX.", "O..X"]);
+
+        // Test
+        // The anti diagonal of O
+        assert!(board.completes_line(&BoardPosition::new(1, 1), 3, is_empty));
+        assert!(!board.completes_line(&BoardPosition::new(1, 1), 4, is_empty));
+        // The diagonal of X
+        assert!(board.completes_line(&BoardPosition::new(3, 2), 3, is_empty));
+        assert!(!board.completes_line(&BoardPosition::new(3, 2), 4, is_empty));
+    }
+
+    #[test]
+    fn empty_fields_make_no_lines() {
+        // Setup
+        let board = board(&["...", "...", "..."]);
+
+        // Test
+        assert_eq!(
+            board.line_length(&BoardPosition::new(1, 1), 1, 0, is_empty),
+            0
+        );
+        assert!(!board.completes_line(&BoardPosition::new(1, 1), 1, is_empty));
+        assert!(!board.completes_line(&BoardPosition::new(0, 0), 3, is_empty));
+    }
+
+    #[test]
+    fn windows() {
+        // Setup
+        let board = board(&["X..", ".O.", "..."]);
+
+        // Act
+        let windows = board.windows(3, owner);
+
+        // Test
+        // 3 rows, 3 columns and 2 diagonals
+        assert_eq!(windows.len(), 8);
+        let open: Vec<&LineWindow<char>> = windows.iter().filter(|w| w.is_open()).collect();
+        assert_eq!(open.len(), 7);
+        let diagonal = windows.iter().find(|w| w.direction == (1, 1)).unwrap();
+        assert!(!diagonal.is_open());
+        assert_eq!((diagonal.count(&'X'), diagonal.count(&'O')), (1, 1));
+        assert_eq!(open.iter().filter(|w| w.count(&'O') == 1).count(), 3);
+    }
+
+    #[test]
+    fn windows_through() {
+        // Setup
+        let board = board(&["XX.X", "...."]);
+
+        // Act
+        let windows = board.windows_through(&BoardPosition::new(1, 0), 3, owner);
+
+        // Test
+        // Two horizontal windows, no vertical or diagonal fits on 2 rows
+        assert_eq!(windows.len(), 2);
+        assert_eq!(windows[0].start, BoardPosition::new(1, 0));
+        assert_eq!(windows[0].count(&'X'), 2);
+        assert_eq!(windows[1].start, BoardPosition::new(0, 0));
+        assert_eq!(windows[1].count(&'X'), 2);
+    }
+}
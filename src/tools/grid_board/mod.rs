@@ -1,16 +1,31 @@
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use crate::tools::grid_board::direction::{Direction, Topology};
 use crate::tools::grid_board::iterator::FieldIterator;
 use crate::tools::grid_board::sliding_window_iterator::SlidingWindowIterator;
+use crate::tools::grid_board::zobrist::{ZobristField, ZobristHash};
 
 pub mod position;
-pub use position::BoardPosition;
+pub use position::{BoardPosition, ColumnLetters};
+pub mod direction;
 pub mod iterator;
+pub mod lines;
+pub mod patterns;
+pub mod regions;
 pub mod sliding_window_iterator;
+pub mod sparse;
+pub mod symmetry;
+pub mod text;
+pub mod zobrist;
 
 // A Board for any game, putting its pieces on a regular grid.
+#[derive(Clone)]
 pub struct Board<FieldContent> {
     fields: Vec<FieldContent>,
     cols: usize,
-    rows: usize
+    rows: usize,
+    topology: Topology,
+    zobrist: Option<ZobristHash<FieldContent>>,
 }
 
 impl<FieldContent> Board<FieldContent> {
@@ -20,6 +35,8 @@ impl<FieldContent> Board<FieldContent> {
             cols,
             rows,
             fields: std::iter::repeat_with(default_content).take(rows*cols).collect(),
+            topology: Topology::Flat,
+            zobrist: None,
         }
     }
 
@@ -31,9 +48,32 @@ impl<FieldContent> Board<FieldContent> {
         self.rows
     }
 
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    // Connect the edges of the board, positions leaving it are wrapped around by get, set and
+    // the iterators
+    pub fn set_topology(&mut self, topology: Topology) {
+        self.topology = topology;
+    }
+
+    // The position on the board, after wrapping around connected edges
+    pub fn wrap(&self, pos: &BoardPosition) -> Option<BoardPosition> {
+        self.topology.wrap(pos, self.rows, self.cols)
+    }
+
+    fn index(&self, pos: &BoardPosition) -> Option<usize> {
+        self.wrap(pos).and_then(|p| p.linear_index(self.rows, self.cols))
+    }
+
     // Set the content of a field
     pub fn set(&mut self, pos: &BoardPosition, value: FieldContent) {
-        if let Some(index) = pos.linear_index(self.rows, self.cols) {
+        if let Some(index) = self.index(pos) {
+            if let Some(zobrist) = &mut self.zobrist {
+                zobrist.toggle(index, &self.fields[index]);
+                zobrist.toggle(index, &value);
+            }
             self.fields[index] = value;
         } else {
             panic!("Invalid insert position");
@@ -42,7 +82,19 @@ impl<FieldContent> Board<FieldContent> {
 
     // Get the content of the specific field
     pub fn get(&self, pos: &BoardPosition) -> Option<&FieldContent> {
-        pos.linear_index(self.rows, self.cols).map(|i| &self.fields[i])
+        self.index(pos).map(|i| &self.fields[i])
+    }
+
+    // Mutable access bypasses the Zobrist hash, so it is only allowed without one
+    pub(crate) fn get_mut(&mut self, pos: &BoardPosition) -> Option<&mut FieldContent> {
+        assert!(self.zobrist.is_none(), "Mutable access to a board with Zobrist hash");
+        let index = self.index(pos)?;
+        Some(&mut self.fields[index])
+    }
+
+    // The Zobrist hash of the board, if enabled with enable_zobrist_hash
+    pub fn zobrist_hash(&self) -> Option<u64> {
+        self.zobrist.as_ref().map(|z| z.hash())
     }
 
     pub fn field_iterator(&self, pos: BoardPosition, row_dir: isize, col_dir: isize)
@@ -55,6 +107,13 @@ impl<FieldContent> Board<FieldContent> {
         )
     }
 
+    // Iterates from the position in the direction, until it leaves the board or, on wrapping
+    // topologies, returns to the start
+    pub fn direction_iterator(&self, pos: BoardPosition, direction: Direction)
+        -> FieldIterator<'_, FieldContent> {
+        self.field_iterator(pos, direction.dx, direction.dy)
+    }
+
     pub fn sliding_window_iterator(&self, pos: BoardPosition, row_dir: isize, col_dir: isize, window_size: usize)
                       -> SlidingWindowIterator<FieldContent> {
         SlidingWindowIterator::new(
@@ -66,3 +125,47 @@ impl<FieldContent> Board<FieldContent> {
         )
     }
 }
+
+impl<FieldContent: ZobristField> Board<FieldContent> {
+    // Starts maintaining a Zobrist hash, that is updated on every set. The keys are generated
+    // from the seed.
+    pub fn enable_zobrist_hash(&mut self, seed: u64) {
+        let mut zobrist = ZobristHash::new(self.fields.len(), seed);
+        for (index, field) in self.fields.iter().enumerate() {
+            zobrist.toggle(index, field);
+        }
+        self.zobrist = Some(zobrist);
+    }
+}
+
+// Boards are equal, if their size, topology and fields are. The Zobrist hash follows from the
+// fields, so it is left out.
+impl<FieldContent: PartialEq> PartialEq for Board<FieldContent> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cols == other.cols
+            && self.rows == other.rows
+            && self.topology == other.topology
+            && self.fields == other.fields
+    }
+}
+
+impl<FieldContent: Eq> Eq for Board<FieldContent> {}
+
+impl<FieldContent: fmt::Debug> fmt::Debug for Board<FieldContent> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Board")
+            .field("cols", &self.cols)
+            .field("rows", &self.rows)
+            .field("topology", &self.topology)
+            .field("fields", &self.fields)
+            .finish()
+    }
+}
+
+impl<FieldContent: Hash> Hash for Board<FieldContent> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.cols.hash(state);
+        self.rows.hash(state);
+        self.fields.hash(state);
+    }
+}
@@ -0,0 +1,276 @@
+// Matching small templates against a board, e.g. the connect-four threat "X X . X". Every
+// character of a template is compared with FieldSymbol::symbol of a field, '?' matches any
+// field and whitespace only separates the fields. Templates with more than one row are matched
+// in all symmetries of the square, templates of a single row in all eight directions.
+use std::collections::HashSet;
+use crate::tools::grid_board::direction::Direction;
+use crate::tools::grid_board::symmetry::Symmetry;
+use crate::tools::grid_board::text::{FieldSymbol, ParseError};
+use crate::tools::grid_board::{Board, BoardPosition};
+
+pub const WILDCARD: char = '?';
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Pattern {
+    rows: usize,
+    cols: usize,
+    // Row by row, None for wildcards
+    cells: Vec<Option<char>>,
+}
+
+// The fields of a match, in the order of the cells of the pattern (row by row)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PatternMatch {
+    pub positions: Vec<BoardPosition>,
+}
+
+impl Pattern {
+    // One template row per line, empty lines are ignored
+    pub fn parse(template: &str) -> Result<Pattern, ParseError> {
+        let mut cells = Vec::new();
+        let mut rows = 0;
+        let mut cols = 0;
+        for (number, line) in template.lines().enumerate() {
+            let row: Vec<Option<char>> = line
+                .chars()
+                .filter(|c| !c.is_whitespace())
+                .map(|c| if c == WILDCARD { None } else { Some(c) })
+                .collect();
+            if row.is_empty() {
+                continue;
+            }
+            if rows > 0 && row.len() != cols {
+                return Err(ParseError::RowLength { line: number + 1 });
+            }
+            cols = row.len();
+            rows += 1;
+            cells.extend(row);
+        }
+        if rows == 0 {
+            return Err(ParseError::Empty);
+        }
+        Ok(Pattern { rows, cols, cells })
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn columns(&self) -> usize {
+        self.cols
+    }
+
+    // The offsets of the cells for every distinct orientation of the pattern
+    fn orientations(&self) -> Vec<Vec<(isize, isize)>> {
+        let origin: Vec<BoardPosition> = (0..self.rows as isize)
+            .flat_map(|y| (0..self.cols as isize).map(move |x| BoardPosition::new(x, y)))
+            .collect();
+        let mut orientations: Vec<Vec<(isize, isize)>> = Symmetry::ALL
+            .iter()
+            .map(|symmetry| {
+                origin
+                    .iter()
+                    .map(|p| {
+                        let t = symmetry.transform(p, self.rows, self.cols);
+                        (t.x(), t.y())
+                    })
+                    .collect()
+            })
+            .collect();
+        if self.rows == 1 {
+            for direction in Direction::DIAGONAL {
+                orientations.push(
+                    (0..self.cols as isize)
+                        .map(|i| (direction.dx * i, direction.dy * i))
+                        .collect(),
+                );
+            }
+        }
+
+        // Orientations covering the same cells with the same content are equal, like a
+        // palindrome read in both directions
+        let mut seen = HashSet::new();
+        orientations.retain(|offsets| {
+            let min_x = offsets.iter().map(|o| o.0).min().unwrap();
+            let min_y = offsets.iter().map(|o| o.1).min().unwrap();
+            let mut normalized: Vec<(isize, isize, Option<char>)> = offsets
+                .iter()
+                .zip(&self.cells)
+                .map(|((x, y), c)| (x - min_x, y - min_y, *c))
+                .collect();
+            normalized.sort();
+            seen.insert(normalized)
+        });
+        orientations
+    }
+}
+
+impl<FieldContent: FieldSymbol> Board<FieldContent> {
+    // All places where the pattern matches the board
+    pub fn find_pattern(&self, pattern: &Pattern) -> Vec<PatternMatch> {
+        let mut matches = Vec::new();
+        for offsets in pattern.orientations() {
+            for y in 0..self.rows as isize {
+                for x in 0..self.cols as isize {
+                    let positions: Vec<BoardPosition> = offsets
+                        .iter()
+                        .map(|(dx, dy)| BoardPosition::new(x + dx, y + dy))
+                        .collect();
+                    let is_match = positions.iter().zip(&pattern.cells).all(|(pos, cell)| {
+                        match (self.get(pos), cell) {
+                            (None, _) => false,
+                            (Some(_), None) => true,
+                            (Some(field), Some(symbol)) => field.symbol() == *symbol,
+                        }
+                    });
+                    if is_match {
+                        matches.push(PatternMatch { positions });
+                    }
+                }
+            }
+        }
+        matches
+    }
+}
+
+// Patterns with weights, for evaluation functions like in Gomoku
+#[derive(Clone, Debug, Default)]
+pub struct PatternTable {
+    pub entries: Vec<(Pattern, i64)>,
+}
+
+impl PatternTable {
+    pub fn new() -> PatternTable {
+        PatternTable {
+            entries: Vec::new(),
+        }
+    }
+
+    // Adds a template, panics if it can't be parsed
+    pub fn add(&mut self, template: &str, weight: i64) {
+        let pattern = Pattern::parse(template).expect("Invalid pattern");
+        self.entries.push((pattern, weight));
+    }
+
+    // The sum of the weights of all matches
+    pub fn score<FieldContent: FieldSymbol>(&self, board: &Board<FieldContent>) -> i64 {
+        self.entries
+            .iter()
+            .map(|(pattern, weight)| board.find_pattern(pattern).len() as i64 * weight)
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tools::grid_board::text::TextFormat;
+
+    fn board(text: &str) -> Board<char> {
+        Board::from_text(text, &TextFormat::plain()).unwrap()
+    }
+
+    #[test]
+    fn parse() {
+        // Act
+        let pattern = Pattern::parse("X X . X").unwrap();
+        let square = Pattern::parse("X ?\n? O").unwrap();
+
+        // Test
+        assert_eq!((pattern.rows(), pattern.columns()), (1, 4));
+        assert_eq!((square.rows(), square.columns()), (2, 2));
+        assert_eq!(
+            Pattern::parse("XX\nX").err(),
+            Some(ParseError::RowLength { line: 2 })
+        );
+        assert_eq!(Pattern::parse(" \n").err(), Some(ParseError::Empty));
+    }
+
+    #[test]
+    fn line_in_all_directions() {
+        // Setup
+        let board = board(
+            "
+            XX.X...
+            .......
+            .X.....
+            ..X....
+            .......
+            ....X..
+            ",
+        );
+        let pattern = Pattern::parse("X X . X").unwrap();
+
+        // Act
+        let matches = board.find_pattern(&pattern);
+
+        // Test
+        assert_eq!(matches.len(), 2);
+        // Horizontal, the empty field is the third cell
+        assert_eq!(matches[0].positions[2], BoardPosition::new(2, 0));
+        // Diagonal
+        assert_eq!(matches[1].positions[2], BoardPosition::new(3, 4));
+    }
+
+    #[test]
+    fn palindromes_match_once() {
+        // Setup
+        let board = board("X.X\n...\nX.X");
+        let pattern = Pattern::parse("X ? X").unwrap();
+
+        // Act
+        let matches = board.find_pattern(&pattern);
+
+        // Test
+        // The four sides and both diagonals, each only in one direction
+        assert_eq!(matches.len(), 6);
+    }
+
+    #[test]
+    fn two_dimensional_symmetries() {
+        // Setup
+        // An eye shape in every corner, in another orientation
+        let board = board(
+            "
+            .X..X.
+            XX..XX
+            ......
+            ......
+            XX..XX
+            .X..X.
+            ",
+        );
+        let pattern = Pattern::parse(". X\nX X").unwrap();
+
+        // Act
+        let matches = board.find_pattern(&pattern);
+
+        // Test
+        assert_eq!(matches.len(), 4);
+        let mut eyes: Vec<(isize, isize)> = matches
+            .iter()
+            .map(|m| {
+                let p = m.positions[0];
+                (p.x(), p.y())
+            })
+            .collect();
+        eyes.sort();
+        assert_eq!(eyes, vec![(0, 0), (0, 5), (5, 0), (5, 5)]);
+    }
+
+    #[test]
+    fn pattern_table() {
+        // Setup
+        let board = board(".XXX.\n.....\n..O..");
+        let mut table = PatternTable::new();
+        table.add(". X X X .", 100);
+        table.add("X X", 1);
+        table.add("O", -5);
+
+        // Act
+        let score = table.score(&board);
+
+        // Test
+        assert_eq!(score, 100 + 2 - 5);
+    }
+}
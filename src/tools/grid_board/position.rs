@@ -1,11 +1,79 @@
 // A position on the regular board, so basically its coordinates.
+//
+// In algebraic notation the column is a letter and the row a number starting at 1, so (4, 3)
+// is "e4". Columns after "z" continue with "aa", "ab", ... like in spreadsheets.
+use std::fmt;
+use std::str::FromStr;
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub struct BoardPosition {
     x: isize,
     y: isize
 }
 
+// The letters naming the columns
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ColumnLetters {
+    // a to z, like chess
+    Chess,
+    // a to z without i, like Go
+    Go,
+}
+
+impl ColumnLetters {
+    fn alphabet(&self) -> Vec<char> {
+        match self {
+            ColumnLetters::Chess => ('a'..='z').collect(),
+            ColumnLetters::Go => ('a'..='z').filter(|c| *c != 'i').collect(),
+        }
+    }
+
+    // The name of the column, None for negative columns
+    pub fn label(&self, x: isize) -> Option<String> {
+        if x < 0 {
+            return None;
+        }
+        let alphabet = self.alphabet();
+        let n = alphabet.len();
+        let mut label = Vec::new();
+        let mut value = x as usize + 1;
+        while value > 0 {
+            value -= 1;
+            label.push(alphabet[value % n]);
+            value /= n;
+        }
+        Some(label.iter().rev().collect())
+    }
+
+    // The column of the name, ignoring the case
+    pub fn column(&self, label: &str) -> Option<isize> {
+        let alphabet = self.alphabet();
+        let mut value: usize = 0;
+        for c in label.chars() {
+            let digit = alphabet.iter().position(|a| *a == c.to_ascii_lowercase())?;
+            value = value.checked_mul(alphabet.len())?.checked_add(digit + 1)?;
+        }
+        if value == 0 {
+            None
+        } else {
+            isize::try_from(value - 1).ok()
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NotationError {
+    pub notation: String,
+}
+
+impl fmt::Display for NotationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid board position '{}'", self.notation)
+    }
+}
+
+impl std::error::Error for NotationError {}
+
 impl BoardPosition {
     pub fn new(x: isize, y: isize) ->BoardPosition {
         BoardPosition {
@@ -13,6 +81,34 @@ impl BoardPosition {
         }
     }
 
+    pub fn x(&self) -> isize {
+        self.x
+    }
+
+    pub fn y(&self) -> isize {
+        self.y
+    }
+
+    // Like "e4", None for negative coordinates
+    pub fn to_notation(&self, letters: ColumnLetters) -> Option<String> {
+        if self.y < 0 {
+            return None;
+        }
+        letters.label(self.x).map(|column| format!("{}{}", column, self.y + 1))
+    }
+
+    pub fn from_notation(notation: &str, letters: ColumnLetters) -> Result<BoardPosition, NotationError> {
+        let error = || NotationError { notation: notation.to_string() };
+        let split = notation.find(|c: char| c.is_ascii_digit()).ok_or_else(error)?;
+        let (column, row) = notation.split_at(split);
+        let x = letters.column(column).ok_or_else(error)?;
+        let y = row.parse::<isize>().map_err(|_| error())?;
+        if y < 1 || row.starts_with('0') {
+            return Err(error());
+        }
+        Ok(BoardPosition::new(x, y - 1))
+    }
+
     pub fn step(&mut self, dx: isize, dy: isize) {
         self.x += dx;
         self.y += dy;
@@ -27,6 +123,24 @@ impl BoardPosition {
     }
 }
 
+// Chess letters, negative coordinates are shown as (x, y)
+impl fmt::Display for BoardPosition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.to_notation(ColumnLetters::Chess) {
+            Some(notation) => write!(f, "{}", notation),
+            None => write!(f, "({}, {})", self.x, self.y),
+        }
+    }
+}
+
+impl FromStr for BoardPosition {
+    type Err = NotationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        BoardPosition::from_notation(s, ColumnLetters::Chess)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -60,4 +174,40 @@ mod test {
         assert_eq!(BoardPosition::new(10,0).linear_index(10,10), None);
         assert_eq!(BoardPosition::new(0,10).linear_index(10,10), None);
     }
+
+    #[test]
+    fn notation() {
+        assert_eq!(BoardPosition::new(4, 3).to_string(), "e4");
+        assert_eq!(BoardPosition::new(26, 18).to_string(), "aa19");
+        assert_eq!(BoardPosition::new(-1, 0).to_string(), "(-1, 0)");
+        assert_eq!("e4".parse::<BoardPosition>(), Ok(BoardPosition::new(4, 3)));
+        assert_eq!("AA19".parse::<BoardPosition>(), Ok(BoardPosition::new(26, 18)));
+        for invalid in ["", "e", "4", "e0", "e04", "4e", "e4x", "e-1"] {
+            assert!(invalid.parse::<BoardPosition>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn go_notation() {
+        // Setup
+        let tengen = BoardPosition::new(9, 9);
+
+        // Test
+        assert_eq!(tengen.to_notation(ColumnLetters::Go), Some("k10".to_string()));
+        assert_eq!(BoardPosition::new(8, 0).to_notation(ColumnLetters::Go), Some("j1".to_string()));
+        assert_eq!(BoardPosition::from_notation("K10", ColumnLetters::Go), Ok(tengen));
+        assert!(BoardPosition::from_notation("i5", ColumnLetters::Go).is_err());
+        assert_eq!(ColumnLetters::Go.label(25), Some("aa".to_string()));
+    }
+
+    #[test]
+    fn round_trip() {
+        for letters in [ColumnLetters::Chess, ColumnLetters::Go] {
+            for x in 0..800 {
+                let p = BoardPosition::new(x, x % 30);
+                let notation = p.to_notation(letters).unwrap();
+                assert_eq!(BoardPosition::from_notation(&notation, letters), Ok(p));
+            }
+        }
+    }
 }
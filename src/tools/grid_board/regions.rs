@@ -0,0 +1,314 @@
+use crate::tools::grid_board::direction::Direction;
+use crate::tools::grid_board::{Board, BoardPosition};
+
+// Which fields count as adjacent
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Neighborhood {
+    // Horizontal and vertical neighbors, like in Go
+    Four,
+    // Including the diagonal neighbors
+    Eight,
+}
+
+impl Neighborhood {
+    pub fn directions(&self) -> &'static [Direction] {
+        match self {
+            Neighborhood::Four => &Direction::ORTHOGONAL,
+            Neighborhood::Eight => &Direction::ALL,
+        }
+    }
+}
+
+// The sides of a board
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Edge {
+    // y = 0
+    Top,
+    Bottom,
+    // x = 0
+    Left,
+    Right,
+}
+
+impl<FieldContent> Board<FieldContent> {
+    // The neighbors of the position, that are on the board, wrapped around connected edges
+    pub fn neighbors(
+        &self,
+        pos: &BoardPosition,
+        neighborhood: Neighborhood,
+    ) -> impl Iterator<Item = BoardPosition> + '_ {
+        let pos = *pos;
+        neighborhood
+            .directions()
+            .iter()
+            .filter_map(move |direction| self.wrap(&direction.from(&pos)))
+    }
+
+    // Is the position on the edge?
+    pub fn is_on_edge(&self, pos: &BoardPosition, edge: Edge) -> bool {
+        match edge {
+            Edge::Top => pos.y() == 0,
+            Edge::Bottom => pos.y() == self.rows as isize - 1,
+            Edge::Left => pos.x() == 0,
+            Edge::Right => pos.x() == self.cols as isize - 1,
+        }
+    }
+
+    // Flood fill from the start with the fields, for which the predicate is true. Empty if the
+    // predicate is false for the start.
+    pub fn flood_fill(
+        &self,
+        start: &BoardPosition,
+        neighborhood: Neighborhood,
+        predicate: impl Fn(&FieldContent) -> bool,
+    ) -> Vec<BoardPosition> {
+        let mut visited = vec![false; self.fields.len()];
+        self.fill(start, neighborhood, &predicate, &mut visited)
+    }
+
+    fn fill(
+        &self,
+        start: &BoardPosition,
+        neighborhood: Neighborhood,
+        predicate: &impl Fn(&FieldContent) -> bool,
+        visited: &mut [bool],
+    ) -> Vec<BoardPosition> {
+        let mut region = Vec::new();
+        let index = match self.index(start) {
+            Some(index) if !visited[index] && predicate(&self.fields[index]) => index,
+            _ => return region,
+        };
+        visited[index] = true;
+        let mut stack = vec![*start];
+        while let Some(pos) = stack.pop() {
+            region.push(pos);
+            for neighbor in self.neighbors(&pos, neighborhood) {
+                let index = neighbor.linear_index(self.rows, self.cols).unwrap();
+                if !visited[index] && predicate(&self.fields[index]) {
+                    visited[index] = true;
+                    stack.push(neighbor);
+                }
+            }
+        }
+        region
+    }
+
+    // All connected components of the fields, for which the predicate is true
+    pub fn components(
+        &self,
+        neighborhood: Neighborhood,
+        predicate: impl Fn(&FieldContent) -> bool,
+    ) -> Vec<Vec<BoardPosition>> {
+        let mut visited = vec![false; self.fields.len()];
+        let mut components = Vec::new();
+        for y in 0..self.rows as isize {
+            for x in 0..self.cols as isize {
+                let region = self.fill(
+                    &BoardPosition::new(x, y),
+                    neighborhood,
+                    &predicate,
+                    &mut visited,
+                );
+                if !region.is_empty() {
+                    components.push(region);
+                }
+            }
+        }
+        components
+    }
+
+    // The fields next to the region, that are not part of it
+    pub fn boundary(
+        &self,
+        region: &[BoardPosition],
+        neighborhood: Neighborhood,
+    ) -> Vec<BoardPosition> {
+        let mut in_region = vec![false; self.fields.len()];
+        for pos in region {
+            if let Some(index) = pos.linear_index(self.rows, self.cols) {
+                in_region[index] = true;
+            }
+        }
+        let mut boundary = Vec::new();
+        for pos in region {
+            for neighbor in self.neighbors(pos, neighborhood) {
+                let index = neighbor.linear_index(self.rows, self.cols).unwrap();
+                if !in_region[index] {
+                    // Mark it, so every field is only added once
+                    in_region[index] = true;
+                    boundary.push(neighbor);
+                }
+            }
+        }
+        boundary
+    }
+
+    // The empty fields next to the group of equal pieces at the position, its liberties in Go
+    pub fn liberties(
+        &self,
+        pos: &BoardPosition,
+        neighborhood: Neighborhood,
+        is_empty: impl Fn(&FieldContent) -> bool,
+    ) -> Vec<BoardPosition>
+    where
+        FieldContent: PartialEq,
+    {
+        let content = match self.get(pos) {
+            None => return Vec::new(),
+            Some(content) => content,
+        };
+        let group = self.flood_fill(pos, neighborhood, |c| c == content);
+        self.boundary(&group, neighborhood)
+            .into_iter()
+            .filter(|b| self.get(b).map(&is_empty).unwrap_or(false))
+            .collect()
+    }
+
+    // The owner of all fields around the region, if there is exactly one, like the territory
+    // of an empty region in Go. The owner function returns None for fields without an owner.
+    pub fn region_owner<Owner: PartialEq>(
+        &self,
+        region: &[BoardPosition],
+        neighborhood: Neighborhood,
+        owner: impl Fn(&FieldContent) -> Option<Owner>,
+    ) -> Option<Owner> {
+        let mut result = None;
+        for pos in self.boundary(region, neighborhood) {
+            let o = owner(self.get(&pos)?)?;
+            match &result {
+                None => result = Some(o),
+                Some(r) if *r != o => return None,
+                _ => {}
+            }
+        }
+        result
+    }
+
+    // Are both edges connected by fields, for which the predicate is true, like a win in Hex?
+    pub fn connects_edges(
+        &self,
+        from: Edge,
+        to: Edge,
+        neighborhood: Neighborhood,
+        predicate: impl Fn(&FieldContent) -> bool,
+    ) -> bool {
+        let mut visited = vec![false; self.fields.len()];
+        for y in 0..self.rows as isize {
+            for x in 0..self.cols as isize {
+                let pos = BoardPosition::new(x, y);
+                if self.is_on_edge(&pos, from)
+                    && self
+                        .fill(&pos, neighborhood, &predicate, &mut visited)
+                        .iter()
+                        .any(|p| self.is_on_edge(p, to))
+                {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn board(rows: &[&str]) -> Board<char> {
+        let mut board = Board::new(rows.len(), rows[0].len(), || '.');
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                board.set(&BoardPosition::new(x as isize, y as isize), c);
+            }
+        }
+        board
+    }
+
+    #[test]
+    fn components() {
+        // Setup
+        let board = board(&["XX..", "..X.", "X..X"]);
+
+        // Act
+        let four = board.components(Neighborhood::Four, |c| *c == 'X');
+        let eight = board.components(Neighborhood::Eight, |c| *c == 'X');
+
+        // Test
+        assert_eq!(four.len(), 4);
+        assert_eq!(four[0].len(), 2);
+        // The diagonal chain from (0, 0) to (3, 2) and the single stone at (0, 2)
+        assert_eq!(eight.len(), 2);
+        assert_eq!(eight[0].len(), 4);
+    }
+
+    #[test]
+    fn flood_fill() {
+        // Setup
+        let board = board(&["..X.", "XXX.", "...."]);
+
+        // Act
+        let top_left =
+            board.flood_fill(&BoardPosition::new(0, 0), Neighborhood::Four, |c| *c == '.');
+        let blocked =
+            board.flood_fill(&BoardPosition::new(2, 0), Neighborhood::Four, |c| *c == '.');
+
+        // Test
+        assert_eq!(top_left.len(), 2);
+        assert!(blocked.is_empty());
+    }
+
+    #[test]
+    fn liberties() {
+        // Setup
+        let board = board(&[".XO..", ".XO..", "O...."]);
+
+        // Act
+        let black = board.liberties(&BoardPosition::new(1, 0), Neighborhood::Four, |c| *c == '.');
+        let white = board.liberties(&BoardPosition::new(2, 1), Neighborhood::Four, |c| *c == '.');
+        let corner = board.liberties(&BoardPosition::new(0, 2), Neighborhood::Four, |c| *c == '.');
+
+        // Test
+        assert_eq!(black.len(), 3);
+        assert_eq!(white.len(), 3);
+        assert_eq!(corner.len(), 2);
+        assert!(corner.contains(&BoardPosition::new(0, 1)));
+    }
+
+    #[test]
+    fn region_owner() {
+        // Setup
+        let board = board(&["..X..", "XXX.O", "..XOO"]);
+        let owner = |c: &char| if *c == '.' { None } else { Some(*c) };
+
+        // Act
+        let regions = board.components(Neighborhood::Four, |c| *c == '.');
+
+        // Test
+        assert_eq!(regions.len(), 3);
+        assert_eq!(
+            board.region_owner(&regions[0], Neighborhood::Four, owner),
+            Some('X')
+        );
+        // Touches X and O
+        assert_eq!(
+            board.region_owner(&regions[1], Neighborhood::Four, owner),
+            None
+        );
+        assert_eq!(
+            board.region_owner(&regions[2], Neighborhood::Four, owner),
+            Some('X')
+        );
+    }
+
+    #[test]
+    fn connects_edges() {
+        // Setup
+        let board = board(&["X...", ".X..", ".XX.", "...X"]);
+
+        // Test
+        assert!(board.connects_edges(Edge::Top, Edge::Bottom, Neighborhood::Eight, |c| *c == 'X'));
+        assert!(!board.connects_edges(Edge::Top, Edge::Bottom, Neighborhood::Four, |c| *c == 'X'));
+        assert!(board.connects_edges(Edge::Left, Edge::Right, Neighborhood::Eight, |c| *c == 'X'));
+        assert!(!board.connects_edges(Edge::Left, Edge::Right, Neighborhood::Four, |c| *c == 'X'));
+    }
+}
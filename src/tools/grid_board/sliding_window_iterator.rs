@@ -4,6 +4,8 @@ use crate::tools::grid_board::position::BoardPosition;
 pub struct SlidingWindowIterator<'a, FieldContent> {
     front_pos: BoardPosition,
     end_pos: BoardPosition,
+    // On wrapping boards the iteration ends, before the end of the window is back at the start
+    start_pos: Option<BoardPosition>,
     current_length: usize,
     full_length: usize,
     col_step: isize,
@@ -16,6 +18,7 @@ impl<'a, FieldContent> SlidingWindowIterator<'a, FieldContent> {
         SlidingWindowIterator {
             front_pos: start_pos.clone(),
             end_pos: start_pos,
+            start_pos: board.wrap(&start_pos),
             current_length: 0,
             full_length: length,
             col_step,
@@ -29,6 +32,13 @@ impl<'a, FieldContent> Iterator for SlidingWindowIterator<'a, FieldContent> {
     type Item=SlidingWindowStep<'a, FieldContent>;
 
     fn next(&mut self) -> Option<Self::Item> {
+        if self.current_length == self.full_length {
+            let mut next_end = self.end_pos;
+            next_end.step(self.row_step, self.col_step);
+            if self.board.wrap(&next_end) == self.start_pos {
+                return None;
+            }
+        }
         match self.board.get(&self.front_pos) {
             None => None,
             Some(new_field) => {
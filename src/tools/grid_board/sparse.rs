@@ -0,0 +1,418 @@
+// An unbounded board, that only stores the fields with content. For games on an infinite grid,
+// like Gomoku without edges or Hive.
+use std::collections::HashMap;
+use crate::tools::grid_board::direction::Direction;
+use crate::tools::grid_board::lines::LINE_DIRECTIONS;
+use crate::tools::grid_board::regions::Neighborhood;
+use crate::tools::grid_board::BoardPosition;
+
+// The smallest rectangle containing all fields with content, the maxima are inclusive
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct BoundingBox {
+    pub min_x: isize,
+    pub min_y: isize,
+    pub max_x: isize,
+    pub max_y: isize,
+}
+
+impl BoundingBox {
+    fn new(pos: &BoardPosition) -> BoundingBox {
+        BoundingBox {
+            min_x: pos.x(),
+            min_y: pos.y(),
+            max_x: pos.x(),
+            max_y: pos.y(),
+        }
+    }
+
+    fn extend(&mut self, pos: &BoardPosition) {
+        self.min_x = self.min_x.min(pos.x());
+        self.min_y = self.min_y.min(pos.y());
+        self.max_x = self.max_x.max(pos.x());
+        self.max_y = self.max_y.max(pos.y());
+    }
+
+    fn is_on_border(&self, pos: &BoardPosition) -> bool {
+        pos.x() == self.min_x
+            || pos.x() == self.max_x
+            || pos.y() == self.min_y
+            || pos.y() == self.max_y
+    }
+
+    // Is the position beyond the box, seen in the direction (dx, dy)? Moving on from it never
+    // enters the box again.
+    fn is_passed(&self, pos: &BoardPosition, dx: isize, dy: isize) -> bool {
+        let passed = |v: isize, min: isize, max: isize, d: isize| match d.signum() {
+            1 => v > max,
+            -1 => v < min,
+            _ => v < min || v > max,
+        };
+        passed(pos.x(), self.min_x, self.max_x, dx) || passed(pos.y(), self.min_y, self.max_y, dy)
+    }
+
+    pub fn contains(&self, pos: &BoardPosition) -> bool {
+        pos.x() >= self.min_x
+            && pos.x() <= self.max_x
+            && pos.y() >= self.min_y
+            && pos.y() <= self.max_y
+    }
+
+    pub fn columns(&self) -> usize {
+        (self.max_x - self.min_x) as usize + 1
+    }
+
+    pub fn rows(&self) -> usize {
+        (self.max_y - self.min_y) as usize + 1
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct SparseBoard<FieldContent> {
+    fields: HashMap<BoardPosition, FieldContent>,
+    bounding_box: Option<BoundingBox>,
+}
+
+impl<FieldContent> SparseBoard<FieldContent> {
+    pub fn new() -> SparseBoard<FieldContent> {
+        SparseBoard {
+            fields: HashMap::new(),
+            bounding_box: None,
+        }
+    }
+
+    // Number of fields with content
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    // None as long as the board is empty
+    pub fn bounding_box(&self) -> Option<BoundingBox> {
+        self.bounding_box
+    }
+
+    // Set the content of a field, any position is valid
+    pub fn set(&mut self, pos: &BoardPosition, value: FieldContent) {
+        match &mut self.bounding_box {
+            None => self.bounding_box = Some(BoundingBox::new(pos)),
+            Some(bounding_box) => bounding_box.extend(pos),
+        }
+        self.fields.insert(*pos, value);
+    }
+
+    // Clears the field and returns its content
+    pub fn remove(&mut self, pos: &BoardPosition) -> Option<FieldContent> {
+        let removed = self.fields.remove(pos);
+        if removed.is_some() && self.bounding_box.is_some_and(|b| b.is_on_border(pos)) {
+            // The box might shrink
+            self.bounding_box = None;
+            for p in self.fields.keys() {
+                match &mut self.bounding_box {
+                    None => self.bounding_box = Some(BoundingBox::new(p)),
+                    Some(bounding_box) => bounding_box.extend(p),
+                }
+            }
+        }
+        removed
+    }
+
+    // Get the content of the specific field, None if it is empty
+    pub fn get(&self, pos: &BoardPosition) -> Option<&FieldContent> {
+        self.fields.get(pos)
+    }
+
+    // All fields with content, in no specific order
+    pub fn iter(&self) -> impl Iterator<Item = (&BoardPosition, &FieldContent)> {
+        self.fields.iter()
+    }
+
+    // The neighbors of the position, the board has no edges
+    pub fn neighbors(
+        &self,
+        pos: &BoardPosition,
+        neighborhood: Neighborhood,
+    ) -> impl Iterator<Item = BoardPosition> {
+        let pos = *pos;
+        neighborhood
+            .directions()
+            .iter()
+            .map(move |direction| direction.from(&pos))
+    }
+
+    // Iterates from the position in the direction (dx, dy), until it is past the bounding box,
+    // as everything beyond it is empty. The position may be outside the box, if the direction
+    // leads into it. Empty fields are None, the direction must not be (0, 0).
+    pub fn field_iterator(
+        &self,
+        pos: BoardPosition,
+        dx: isize,
+        dy: isize,
+    ) -> SparseFieldIterator<'_, FieldContent> {
+        SparseFieldIterator {
+            pos,
+            dx,
+            dy,
+            board: self,
+        }
+    }
+
+    pub fn direction_iterator(
+        &self,
+        pos: BoardPosition,
+        direction: Direction,
+    ) -> SparseFieldIterator<'_, FieldContent> {
+        self.field_iterator(pos, direction.dx, direction.dy)
+    }
+
+    // Slides a window of window_size fields over the fields of field_iterator
+    pub fn sliding_window_iterator(
+        &self,
+        pos: BoardPosition,
+        dx: isize,
+        dy: isize,
+        window_size: usize,
+    ) -> SparseSlidingWindowIterator<'_, FieldContent> {
+        SparseSlidingWindowIterator {
+            fields: self.field_iterator(pos, dx, dy),
+            end_pos: pos,
+            current_length: 0,
+            full_length: window_size,
+        }
+    }
+
+    // Number of fields in the line through the position in direction (dx, dy), that have the
+    // same content as the position
+    pub fn line_length(&self, pos: &BoardPosition, dx: isize, dy: isize) -> usize
+    where
+        FieldContent: PartialEq,
+    {
+        let content = match self.get(pos) {
+            None => return 0,
+            Some(content) => content,
+        };
+        let mut length = 1;
+        for (sx, sy) in [(dx, dy), (-dx, -dy)] {
+            length += self
+                .field_iterator(*pos, sx, sy)
+                .skip(1)
+                .take_while(|c| *c == Some(content))
+                .count();
+        }
+        length
+    }
+
+    // Is the piece at the position part of a line of at least k equal pieces?
+    pub fn completes_line(&self, pos: &BoardPosition, k: usize) -> bool
+    where
+        FieldContent: PartialEq,
+    {
+        LINE_DIRECTIONS
+            .iter()
+            .any(|(dx, dy)| self.line_length(pos, *dx, *dy) >= k)
+    }
+}
+
+impl<FieldContent> Default for SparseBoard<FieldContent> {
+    fn default() -> Self {
+        SparseBoard::new()
+    }
+}
+
+pub struct SparseFieldIterator<'a, FieldContent> {
+    pos: BoardPosition,
+    dx: isize,
+    dy: isize,
+    board: &'a SparseBoard<FieldContent>,
+}
+
+impl<'a, FieldContent> Iterator for SparseFieldIterator<'a, FieldContent> {
+    type Item = Option<&'a FieldContent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self
+            .board
+            .bounding_box?
+            .is_passed(&self.pos, self.dx, self.dy)
+        {
+            return None;
+        }
+        let result = self.board.get(&self.pos);
+        self.pos.step(self.dx, self.dy);
+        Some(result)
+    }
+}
+
+pub struct SparseSlidingWindowIterator<'a, FieldContent> {
+    fields: SparseFieldIterator<'a, FieldContent>,
+    end_pos: BoardPosition,
+    current_length: usize,
+    full_length: usize,
+}
+
+impl<'a, FieldContent> Iterator for SparseSlidingWindowIterator<'a, FieldContent> {
+    type Item = SparseSlidingWindowStep<'a, FieldContent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let front_pos = self.fields.pos;
+        let new_field = self.fields.next()?;
+        let removed_field = if self.current_length == self.full_length {
+            let removed_field = self.fields.board.get(&self.end_pos);
+            self.end_pos.step(self.fields.dx, self.fields.dy);
+            removed_field
+        } else {
+            self.current_length += 1;
+            None
+        };
+        Some(SparseSlidingWindowStep {
+            new_field,
+            removed_field,
+            length: self.current_length,
+            is_full: self.current_length == self.full_length,
+            front_pos,
+            end_pos: self.end_pos,
+        })
+    }
+}
+
+pub struct SparseSlidingWindowStep<'a, FieldContent> {
+    // The field sliding into the window, None if it is empty
+    pub new_field: Option<&'a FieldContent>,
+    // The field being removed from the window, None if there is none or it is empty
+    pub removed_field: Option<&'a FieldContent>,
+    // The current length of the window
+    pub length: usize,
+    // Is the window full? (this is equal to length==window_size)
+    pub is_full: bool,
+    // The position of the new field
+    pub front_pos: BoardPosition,
+    // The position of the oldest field still in the window
+    pub end_pos: BoardPosition,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn bounding_box() {
+        // Setup
+        let mut board = SparseBoard::new();
+
+        // Act
+        board.set(&BoardPosition::new(-5, 3), 'X');
+        board.set(&BoardPosition::new(100, -2), 'O');
+        board.set(&BoardPosition::new(0, 0), 'X');
+
+        // Test
+        let bounding_box = board.bounding_box().unwrap();
+        assert_eq!((bounding_box.min_x, bounding_box.max_x), (-5, 100));
+        assert_eq!((bounding_box.min_y, bounding_box.max_y), (-2, 3));
+        assert_eq!((bounding_box.columns(), bounding_box.rows()), (106, 6));
+        assert_eq!(board.len(), 3);
+        assert_eq!(board.get(&BoardPosition::new(1, 1)), None);
+    }
+
+    #[test]
+    fn remove_shrinks_bounding_box() {
+        // Setup
+        let mut board = SparseBoard::new();
+        board.set(&BoardPosition::new(0, 0), 1);
+        board.set(&BoardPosition::new(1, 1), 2);
+        board.set(&BoardPosition::new(9, 1), 3);
+
+        // Act
+        let removed = board.remove(&BoardPosition::new(9, 1));
+
+        // Test
+        assert_eq!(removed, Some(3));
+        assert_eq!(board.bounding_box().unwrap().max_x, 1);
+        board.remove(&BoardPosition::new(0, 0));
+        board.remove(&BoardPosition::new(1, 1));
+        assert!(board.is_empty());
+        assert_eq!(board.bounding_box(), None);
+    }
+
+    #[test]
+    fn field_iterator() {
+        // Setup
+        let mut board = SparseBoard::new();
+        board.set(&BoardPosition::new(-1, -1), 'X');
+        board.set(&BoardPosition::new(1, 1), 'O');
+
+        // Act
+        let fields: Vec<Option<&char>> = board
+            .direction_iterator(BoardPosition::new(-1, -1), Direction::SOUTH_EAST)
+            .collect();
+
+        // Test
+        assert_eq!(fields, vec![Some(&'X'), None, Some(&'O')]);
+        // From outside into the box and through it, but not away from it
+        assert_eq!(
+            board
+                .field_iterator(BoardPosition::new(-3, 0), 1, 0)
+                .collect::<Vec<_>>(),
+            vec![None, None, None, None, None]
+        );
+        assert_eq!(
+            board
+                .field_iterator(BoardPosition::new(1, -4), 0, 1)
+                .filter(|f| f.is_some())
+                .count(),
+            1
+        );
+        assert_eq!(
+            board
+                .field_iterator(BoardPosition::new(-3, 0), -1, 0)
+                .count(),
+            0
+        );
+        assert_eq!(
+            board
+                .neighbors(&BoardPosition::new(-1, -1), Neighborhood::Eight)
+                .count(),
+            8
+        );
+    }
+
+    #[test]
+    fn sliding_window() {
+        // Setup
+        let mut board = SparseBoard::new();
+        for x in [0, 1, 3, 4, 5] {
+            board.set(&BoardPosition::new(x, 0), 'X');
+        }
+        board.set(&BoardPosition::new(2, 7), 'O');
+
+        // Act
+        let mut count = 0;
+        let mut counts = Vec::new();
+        for step in board.sliding_window_iterator(BoardPosition::new(-1, 0), 1, 0, 3) {
+            count += step.new_field.is_some() as usize;
+            count -= step.removed_field.is_some() as usize;
+            if step.is_full {
+                counts.push((step.end_pos.x(), count));
+            }
+        }
+
+        // Test
+        assert_eq!(counts, vec![(-1, 2), (0, 2), (1, 2), (2, 2), (3, 3)]);
+    }
+
+    #[test]
+    fn gomoku_without_edges() {
+        // Setup
+        let mut board = SparseBoard::new();
+        for i in 0..5 {
+            board.set(&BoardPosition::new(-1000 + i, 7 - i), 'X');
+        }
+        board.set(&BoardPosition::new(-995, 2), 'O');
+
+        // Test
+        assert!(board.completes_line(&BoardPosition::new(-998, 5), 5));
+        assert!(!board.completes_line(&BoardPosition::new(-998, 5), 6));
+        assert_eq!(board.line_length(&BoardPosition::new(-995, 2), 1, -1), 1);
+    }
+}
@@ -0,0 +1,275 @@
+use crate::tools::grid_board::{Board, BoardPosition};
+
+// The symmetries of a rectangle and for square boards additionally of a square (the dihedral
+// group). Quarter turns and the diagonal reflections swap rows and columns, so they map a board
+// onto itself only if it is square.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Symmetry {
+    Identity,
+    // (x, y) -> (rows - 1 - y, x)
+    Rotate90,
+    Rotate180,
+    Rotate270,
+    // Left and right are swapped, like mirroring connect-four
+    MirrorX,
+    // Top and bottom are swapped
+    MirrorY,
+    // (x, y) -> (y, x)
+    Transpose,
+    AntiTranspose,
+}
+
+impl Symmetry {
+    pub const ALL: [Symmetry; 8] = [
+        Symmetry::Identity,
+        Symmetry::Rotate90,
+        Symmetry::Rotate180,
+        Symmetry::Rotate270,
+        Symmetry::MirrorX,
+        Symmetry::MirrorY,
+        Symmetry::Transpose,
+        Symmetry::AntiTranspose,
+    ];
+
+    // The symmetries, that keep the size of every board
+    pub const RECTANGLE: [Symmetry; 4] = [
+        Symmetry::Identity,
+        Symmetry::Rotate180,
+        Symmetry::MirrorX,
+        Symmetry::MirrorY,
+    ];
+
+    // Does it swap rows and columns?
+    pub fn swaps_dimensions(&self) -> bool {
+        matches!(
+            self,
+            Symmetry::Rotate90
+                | Symmetry::Rotate270
+                | Symmetry::Transpose
+                | Symmetry::AntiTranspose
+        )
+    }
+
+    // The symmetry undoing this one
+    pub fn inverse(&self) -> Symmetry {
+        match self {
+            Symmetry::Rotate90 => Symmetry::Rotate270,
+            Symmetry::Rotate270 => Symmetry::Rotate90,
+            other => *other,
+        }
+    }
+
+    // Where a position of a board with the given size ends up
+    pub fn transform(&self, pos: &BoardPosition, rows: usize, cols: usize) -> BoardPosition {
+        let (x, y) = (pos.x(), pos.y());
+        let max_x = cols as isize - 1;
+        let max_y = rows as isize - 1;
+        match self {
+            Symmetry::Identity => BoardPosition::new(x, y),
+            Symmetry::Rotate90 => BoardPosition::new(max_y - y, x),
+            Symmetry::Rotate180 => BoardPosition::new(max_x - x, max_y - y),
+            Symmetry::Rotate270 => BoardPosition::new(y, max_x - x),
+            Symmetry::MirrorX => BoardPosition::new(max_x - x, y),
+            Symmetry::MirrorY => BoardPosition::new(x, max_y - y),
+            Symmetry::Transpose => BoardPosition::new(y, x),
+            Symmetry::AntiTranspose => BoardPosition::new(max_y - y, max_x - x),
+        }
+    }
+}
+
+impl<FieldContent> Board<FieldContent> {
+    // All symmetries mapping the board onto itself: 8 for square boards, 4 otherwise
+    pub fn symmetries(&self) -> &'static [Symmetry] {
+        if self.rows == self.cols {
+            &Symmetry::ALL
+        } else {
+            &Symmetry::RECTANGLE
+        }
+    }
+
+    // A transformed copy of the board, rows and columns are swapped if the symmetry does
+    pub fn transformed(&self, symmetry: Symmetry) -> Board<FieldContent>
+    where
+        FieldContent: Clone,
+    {
+        let (rows, cols) = if symmetry.swaps_dimensions() {
+            (self.cols, self.rows)
+        } else {
+            (self.rows, self.cols)
+        };
+        let mut fields = self.fields.clone();
+        for y in 0..self.rows as isize {
+            for x in 0..self.cols as isize {
+                let pos = BoardPosition::new(x, y);
+                let target = symmetry.transform(&pos, self.rows, self.cols);
+                let index = target.linear_index(rows, cols).unwrap();
+                fields[index] =
+                    self.fields[pos.linear_index(self.rows, self.cols).unwrap()].clone();
+            }
+        }
+        Board {
+            fields,
+            cols,
+            rows,
+            topology: self.topology,
+            zobrist: None,
+        }
+    }
+
+    // The smallest of all symmetric boards, comparing the fields row by row, and the symmetry
+    // transforming this board into it. Symmetric positions have the same canonical form, so
+    // they can share an entry in a transposition table. Moves are mapped with
+    // Symmetry::transform.
+    pub fn canonical(&self) -> (Board<FieldContent>, Symmetry)
+    where
+        FieldContent: Clone + Ord,
+    {
+        self.canonical_with(self.symmetries())
+    }
+
+    // Like canonical, but only with the given symmetries of the game. E.g. connect-four has
+    // gravity, so only [Symmetry::Identity, Symmetry::MirrorX] keep positions equivalent.
+    // Panics if a symmetry swaps rows and columns of a board, that is not square.
+    pub fn canonical_with(&self, symmetries: &[Symmetry]) -> (Board<FieldContent>, Symmetry)
+    where
+        FieldContent: Clone + Ord,
+    {
+        let mut best = (self.transformed(Symmetry::Identity), Symmetry::Identity);
+        for symmetry in symmetries {
+            assert!(
+                self.rows == self.cols || !symmetry.swaps_dimensions(),
+                "The symmetry does not map the board onto itself"
+            );
+            let board = self.transformed(*symmetry);
+            if board.fields < best.0.fields {
+                best = (board, *symmetry);
+            }
+        }
+        best
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn board(rows: &[&str]) -> Board<char> {
+        let mut board = Board::new(rows.len(), rows[0].len(), || '.');
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                board.set(&BoardPosition::new(x as isize, y as isize), c);
+            }
+        }
+        board
+    }
+
+    fn rows(board: &Board<char>) -> Vec<String> {
+        (0..board.rows() as isize)
+            .map(|y| {
+                board
+                    .field_iterator(BoardPosition::new(0, y), 1, 0)
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn transformations() {
+        // Setup
+        let board = board(&["ab.", "..c"]);
+
+        // Test
+        assert_eq!(
+            rows(&board.transformed(Symmetry::Rotate90)),
+            [".a", ".b", "c."]
+        );
+        assert_eq!(
+            rows(&board.transformed(Symmetry::Rotate180)),
+            ["c..", ".ba"]
+        );
+        assert_eq!(
+            rows(&board.transformed(Symmetry::Rotate270)),
+            [".c", "b.", "a."]
+        );
+        assert_eq!(rows(&board.transformed(Symmetry::MirrorX)), [".ba", "c.."]);
+        assert_eq!(rows(&board.transformed(Symmetry::MirrorY)), ["..c", "ab."]);
+        assert_eq!(
+            rows(&board.transformed(Symmetry::Transpose)),
+            ["a.", "b.", ".c"]
+        );
+        assert_eq!(
+            rows(&board.transformed(Symmetry::AntiTranspose)),
+            ["c.", ".b", ".a"]
+        );
+    }
+
+    #[test]
+    fn inverse() {
+        // Setup
+        let board = board(&["ab.", "..c"]);
+
+        for symmetry in Symmetry::ALL {
+            // Act
+            let transformed = board.transformed(symmetry);
+            let back = transformed.transformed(symmetry.inverse());
+
+            // Test
+            assert_eq!(rows(&back), rows(&board));
+            let pos = BoardPosition::new(1, 0);
+            let target = symmetry.transform(&pos, board.rows(), board.columns());
+            assert_eq!(transformed.get(&target), Some(&'b'));
+        }
+    }
+
+    #[test]
+    fn canonical_tic_tac_toe() {
+        // Setup
+        // The same position: X in a corner, O in the center
+        let corners = [
+            board(&["X..", ".O.", "..."]),
+            board(&["..X", ".O.", "..."]),
+            board(&["...", ".O.", "..X"]),
+            board(&["...", ".O.", "X.."]),
+        ];
+
+        // Act
+        let canonical: Vec<(Vec<String>, Symmetry)> = corners
+            .iter()
+            .map(|b| {
+                let (c, s) = b.canonical();
+                (rows(&c), s)
+            })
+            .collect();
+
+        // Test
+        for (c, _) in &canonical {
+            assert_eq!(c, &canonical[0].0);
+        }
+        assert_eq!(canonical[0].0, ["...", ".O.", "..X"]);
+        assert_eq!(canonical[2].1, Symmetry::Identity);
+    }
+
+    #[test]
+    fn canonical_connect_four_mirror() {
+        // Setup
+        let connect_four = [Symmetry::Identity, Symmetry::MirrorX];
+        let left = board(&[".......", "X......", "XO....."]);
+        let right = board(&[".......", "......X", ".....OX"]);
+        // The vertical flip would be smaller, but is no symmetry with gravity
+        let top = board(&["...X...", ".......", "......."]);
+
+        // Act
+        let (canonical_left, symmetry_left) = left.canonical_with(&connect_four);
+        let (canonical_right, symmetry_right) = right.canonical_with(&connect_four);
+        let (canonical_top, symmetry_top) = top.canonical_with(&connect_four);
+
+        // Test
+        assert_eq!(left.symmetries().len(), 4);
+        assert_eq!(rows(&canonical_left), rows(&canonical_right));
+        assert_eq!(symmetry_left, Symmetry::MirrorX);
+        assert_eq!(symmetry_right, Symmetry::Identity);
+        assert_eq!(rows(&canonical_top), rows(&top));
+        assert_eq!(symmetry_top, Symmetry::Identity);
+        assert_ne!(top.canonical().1, Symmetry::Identity);
+    }
+}
@@ -0,0 +1,329 @@
+// Rendering boards as text and parsing them back, e.g. a tic-tac-toe board with coordinates:
+//
+// 3 . . O
+// 2 . X .
+// 1 X . .
+//   a b c
+//
+// Columns are labelled with letters from a (see ColumnLetters::Chess), rows are numbered from 1.
+use std::fmt;
+use crate::tools::grid_board::direction::Topology;
+use crate::tools::grid_board::{Board, BoardPosition, ColumnLetters};
+
+// The character for the content of a field
+pub trait FieldSymbol: Sized {
+    fn symbol(&self) -> char;
+
+    // None for characters, that don't stand for a field content
+    fn from_symbol(symbol: char) -> Option<Self>;
+}
+
+impl FieldSymbol for char {
+    fn symbol(&self) -> char {
+        *self
+    }
+
+    fn from_symbol(symbol: char) -> Option<char> {
+        Some(symbol)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TextFormat {
+    // Row numbers in front of the rows and column letters below them
+    pub coordinates: bool,
+    // A space between the fields of a row
+    pub spaced: bool,
+    // Print y = 0 as the last row, like the first rank of a chess board
+    pub first_row_at_bottom: bool,
+}
+
+impl TextFormat {
+    pub fn new() -> TextFormat {
+        TextFormat {
+            coordinates: true,
+            spaced: true,
+            first_row_at_bottom: true,
+        }
+    }
+
+    // Just the symbols, one line per row from y = 0, handy for string literals in tests
+    pub fn plain() -> TextFormat {
+        TextFormat {
+            coordinates: false,
+            spaced: false,
+            first_row_at_bottom: false,
+        }
+    }
+
+    fn row_order(&self, rows: usize) -> Vec<usize> {
+        if self.first_row_at_bottom {
+            (0..rows).rev().collect()
+        } else {
+            (0..rows).collect()
+        }
+    }
+}
+
+impl Default for TextFormat {
+    fn default() -> Self {
+        TextFormat::new()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseError {
+    // No rows
+    Empty,
+    // The line has another number of fields than the first row
+    RowLength { line: usize },
+    UnknownSymbol { line: usize, symbol: char },
+    // Row number or column letters don't match the board
+    InvalidCoordinates { line: usize },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Empty => write!(f, "the board has no rows"),
+            ParseError::RowLength { line } => write!(f, "wrong number of fields in line {}", line),
+            ParseError::UnknownSymbol { line, symbol } => {
+                write!(f, "unknown symbol '{}' in line {}", symbol, line)
+            }
+            ParseError::InvalidCoordinates { line } => {
+                write!(f, "invalid coordinates in line {}", line)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl<FieldContent: FieldSymbol> Board<FieldContent> {
+    pub fn to_text(&self, format: &TextFormat) -> String {
+        let label_width = self.rows.to_string().len();
+        let separator = if format.spaced { " " } else { "" };
+        let mut text = String::new();
+        for y in format.row_order(self.rows) {
+            if format.coordinates {
+                text += &format!("{:>width$} ", y + 1, width = label_width);
+            }
+            let symbols: Vec<String> = (0..self.cols)
+                .map(|x| {
+                    self.fields[BoardPosition::new(x as isize, y as isize)
+                        .linear_index(self.rows, self.cols)
+                        .unwrap()]
+                    .symbol()
+                    .to_string()
+                })
+                .collect();
+            text += &symbols.join(separator);
+            text.push('\n');
+        }
+        if format.coordinates {
+            let labels: Vec<String> = (0..self.cols as isize)
+                .filter_map(|x| ColumnLetters::Chess.label(x))
+                .collect();
+            text += &" ".repeat(label_width + 1);
+            text += &labels.join(separator);
+            text.push('\n');
+        }
+        text
+    }
+
+    // Parses the output of to_text with the same format. Empty lines and leading or trailing
+    // whitespace are ignored.
+    pub fn from_text(text: &str, format: &TextFormat) -> Result<Board<FieldContent>, ParseError> {
+        let mut lines: Vec<(usize, &str)> = text
+            .lines()
+            .enumerate()
+            .map(|(number, line)| (number + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty())
+            .collect();
+        let labels = if format.coordinates {
+            lines.pop()
+        } else {
+            None
+        };
+        if lines.is_empty() {
+            return Err(ParseError::Empty);
+        }
+        let rows = lines.len();
+
+        let mut parsed_rows: Vec<Vec<FieldContent>> = Vec::with_capacity(rows);
+        for (index, (line_number, line)) in lines.iter().enumerate() {
+            let mut fields = *line;
+            if format.coordinates {
+                let (label, rest) = fields
+                    .split_once(char::is_whitespace)
+                    .ok_or(ParseError::InvalidCoordinates { line: *line_number })?;
+                let expected = if format.first_row_at_bottom {
+                    rows - index
+                } else {
+                    index + 1
+                };
+                if label.parse::<usize>() != Ok(expected) {
+                    return Err(ParseError::InvalidCoordinates { line: *line_number });
+                }
+                fields = rest.trim_start();
+            }
+            let symbols: Vec<char> = if format.spaced {
+                fields.split_whitespace().flat_map(|s| s.chars()).collect()
+            } else {
+                fields.chars().collect()
+            };
+            let row = symbols
+                .into_iter()
+                .map(|symbol| {
+                    FieldContent::from_symbol(symbol).ok_or(ParseError::UnknownSymbol {
+                        line: *line_number,
+                        symbol,
+                    })
+                })
+                .collect::<Result<Vec<FieldContent>, ParseError>>()?;
+            if !parsed_rows.is_empty() && row.len() != parsed_rows[0].len() {
+                return Err(ParseError::RowLength { line: *line_number });
+            }
+            parsed_rows.push(row);
+        }
+        let cols = parsed_rows[0].len();
+
+        if let Some((line_number, line)) = labels {
+            let expected: String = (0..cols as isize)
+                .filter_map(|x| ColumnLetters::Chess.label(x))
+                .collect();
+            if line.split_whitespace().collect::<String>() != expected {
+                return Err(ParseError::InvalidCoordinates { line: line_number });
+            }
+        }
+
+        let order = format.row_order(rows);
+        let mut fields: Vec<Option<FieldContent>> = (0..rows * cols).map(|_| None).collect();
+        for (row, y) in parsed_rows.into_iter().zip(order) {
+            for (x, content) in row.into_iter().enumerate() {
+                fields[x + y * cols] = Some(content);
+            }
+        }
+        Ok(Board {
+            fields: fields.into_iter().map(|f| f.unwrap()).collect(),
+            cols,
+            rows,
+            topology: Topology::Flat,
+            zobrist: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    enum Field {
+        Empty,
+        Black,
+        White,
+    }
+
+    impl FieldSymbol for Field {
+        fn symbol(&self) -> char {
+            match self {
+                Field::Empty => '.',
+                Field::Black => 'X',
+                Field::White => 'O',
+            }
+        }
+
+        fn from_symbol(symbol: char) -> Option<Field> {
+            match symbol {
+                '.' => Some(Field::Empty),
+                'X' => Some(Field::Black),
+                'O' => Some(Field::White),
+                _ => None,
+            }
+        }
+    }
+
+    #[test]
+    fn render_with_coordinates() {
+        // Setup
+        let mut board = Board::new(3, 3, || Field::Empty);
+        board.set(&BoardPosition::new(0, 0), Field::Black);
+        board.set(&BoardPosition::new(1, 1), Field::White);
+
+        // Act
+        let text = board.to_text(&TextFormat::new());
+
+        // Test
+        assert_eq!(text, "3 . . .\n2 . O .\n1 X . .\n  a b c\n");
+    }
+
+    #[test]
+    fn round_trip() {
+        // Setup
+        let text = "
+            10 . . . . X
+             9 . . . O .
+             8 . . . . .
+             7 . . . . .
+             6 . . . . .
+             5 . . . . .
+             4 . . . . .
+             3 . . . . .
+             2 . . . . .
+             1 X . . . .
+               a b c d e
+        ";
+
+        // Act
+        let board: Board<Field> = Board::from_text(text, &TextFormat::new()).unwrap();
+
+        // Test
+        assert_eq!((board.rows(), board.columns()), (10, 5));
+        assert_eq!(board.get(&BoardPosition::new(0, 0)), Some(&Field::Black));
+        assert_eq!(board.get(&BoardPosition::new(4, 9)), Some(&Field::Black));
+        assert_eq!(board.get(&BoardPosition::new(3, 8)), Some(&Field::White));
+        let rendered = board.to_text(&TextFormat::new());
+        let reparsed: Board<Field> = Board::from_text(&rendered, &TextFormat::new()).unwrap();
+        assert_eq!(reparsed.to_text(&TextFormat::new()), rendered);
+    }
+
+    #[test]
+    fn plain() {
+        // Act
+        let board: Board<char> = Board::from_text("ab\ncd\nef", &TextFormat::plain()).unwrap();
+
+        // Test
+        assert_eq!(board.get(&BoardPosition::new(1, 0)), Some(&'b'));
+        assert_eq!(board.get(&BoardPosition::new(0, 2)), Some(&'e'));
+        assert_eq!(board.to_text(&TextFormat::plain()), "ab\ncd\nef\n");
+    }
+
+    #[test]
+    fn parse_errors() {
+        let format = TextFormat::new();
+        assert_eq!(
+            Board::<Field>::from_text("", &format).err(),
+            Some(ParseError::Empty)
+        );
+        assert_eq!(
+            Board::<Field>::from_text("2 . .\n1 . ?\n  a b", &format).err(),
+            Some(ParseError::UnknownSymbol {
+                line: 2,
+                symbol: '?'
+            })
+        );
+        assert_eq!(
+            Board::<Field>::from_text("2 . .\n1 .\n  a b", &format).err(),
+            Some(ParseError::RowLength { line: 2 })
+        );
+        assert_eq!(
+            Board::<Field>::from_text("1 . .\n2 . .\n  a b", &format).err(),
+            Some(ParseError::InvalidCoordinates { line: 1 })
+        );
+        assert_eq!(
+            Board::<Field>::from_text("2 . .\n1 . .\n  a c", &format).err(),
+            Some(ParseError::InvalidCoordinates { line: 3 })
+        );
+    }
+}
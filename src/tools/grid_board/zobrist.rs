@@ -0,0 +1,138 @@
+use crate::tools::random::Random;
+
+// Field contents, that can be part of a Zobrist hash (https://www.chessprogramming.org/Zobrist_Hashing).
+// Each content maps to a small index below KINDS, contents without an index (like an empty
+// field) do not change the hash.
+pub trait ZobristField {
+    const KINDS: usize;
+    fn zobrist_index(&self) -> Option<usize>;
+}
+
+// The random keys of all (field, kind) pairs and the current hash of a board.
+#[derive(Clone)]
+pub struct ZobristHash<FieldContent> {
+    keys: Vec<u64>,
+    kinds: usize,
+    index: fn(&FieldContent) -> Option<usize>,
+    hash: u64,
+}
+
+impl<FieldContent: ZobristField> ZobristHash<FieldContent> {
+    // The keys only depend on the seed and the number of fields, so boards of the same size
+    // with the same seed get the same hash for the same position.
+    pub fn new(fields: usize, seed: u64) -> ZobristHash<FieldContent> {
+        let mut random = Random::new(seed);
+        ZobristHash {
+            keys: (0..fields * FieldContent::KINDS).map(|_| random.next_u64()).collect(),
+            kinds: FieldContent::KINDS,
+            index: FieldContent::zobrist_index,
+            hash: 0,
+        }
+    }
+}
+
+impl<FieldContent> ZobristHash<FieldContent> {
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    // Adds or removes the content at the field
+    pub fn toggle(&mut self, field: usize, content: &FieldContent) {
+        if let Some(kind) = (self.index)(content) {
+            assert!(kind < self.kinds, "Zobrist index out of range");
+            self.hash ^= self.keys[field * self.kinds + kind];
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tools::grid_board::{Board, BoardPosition};
+    use super::*;
+
+    #[derive(Clone, Copy, PartialEq, Debug)]
+    enum Field {
+        Empty,
+        Black,
+        White,
+    }
+
+    impl ZobristField for Field {
+        const KINDS: usize = 2;
+
+        fn zobrist_index(&self) -> Option<usize> {
+            match self {
+                Field::Empty => None,
+                Field::Black => Some(0),
+                Field::White => Some(1),
+            }
+        }
+    }
+
+    fn board_with_hash(seed: u64) -> Board<Field> {
+        let mut board = Board::new(3, 3, || Field::Empty);
+        board.enable_zobrist_hash(seed);
+        board
+    }
+
+    #[test]
+    fn disabled_by_default() {
+        let board = Board::new(3, 3, || Field::Empty);
+        assert_eq!(board.zobrist_hash(), None);
+    }
+
+    #[test]
+    fn set_and_reset_restores_hash() {
+        // Setup
+        let mut board = board_with_hash(1);
+        let empty_hash = board.zobrist_hash();
+
+        // Act
+        board.set(&BoardPosition::new(1, 2), Field::Black);
+        let black_hash = board.zobrist_hash();
+        board.set(&BoardPosition::new(1, 2), Field::White);
+        let white_hash = board.zobrist_hash();
+        board.set(&BoardPosition::new(1, 2), Field::Empty);
+
+        // Test
+        assert_eq!(empty_hash, Some(0));
+        assert_ne!(black_hash, empty_hash);
+        assert_ne!(white_hash, black_hash);
+        assert_eq!(board.zobrist_hash(), empty_hash);
+    }
+
+    #[test]
+    fn hash_independent_of_move_order() {
+        // Setup
+        let mut board1 = board_with_hash(7);
+        let mut board2 = board_with_hash(7);
+
+        // Act
+        board1.set(&BoardPosition::new(0, 0), Field::Black);
+        board1.set(&BoardPosition::new(2, 1), Field::White);
+        board2.set(&BoardPosition::new(2, 1), Field::White);
+        board2.set(&BoardPosition::new(0, 0), Field::Black);
+
+        // Test
+        assert_eq!(board1.zobrist_hash(), board2.zobrist_hash());
+    }
+
+    #[test]
+    fn enabling_hashes_existing_pieces() {
+        // Setup
+        let mut board1 = Board::new(3, 3, || Field::Empty);
+        board1.set(&BoardPosition::new(1, 1), Field::White);
+        let mut board2 = board_with_hash(3);
+        let mut board3 = board_with_hash(4);
+
+        // Act
+        board1.enable_zobrist_hash(3);
+        board2.set(&BoardPosition::new(1, 1), Field::White);
+        board3.set(&BoardPosition::new(1, 1), Field::White);
+
+        // Test
+        assert_eq!(board1.zobrist_hash(), board2.zobrist_hash());
+        // Other seed, other keys
+        assert_ne!(board1.zobrist_hash(), board3.zobrist_hash());
+    }
+}
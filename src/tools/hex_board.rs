@@ -0,0 +1,270 @@
+// A board of hexagonal fields, for games like Hex, Havannah or Abalone.
+//
+// Positions use axial coordinates (q, r), the third cube coordinate s = -q - r is implied
+// (https://www.redblobgames.com/grids/hexagons/). Every field has six neighbors, in the
+// directions of HEX_DIRECTIONS.
+
+// The six directions (dq, dr), counter clockwise starting east
+pub const HEX_DIRECTIONS: [(isize, isize); 6] =
+    [(1, 0), (1, -1), (0, -1), (-1, 0), (-1, 1), (0, 1)];
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct HexPosition {
+    q: isize,
+    r: isize,
+}
+
+impl HexPosition {
+    pub fn new(q: isize, r: isize) -> HexPosition {
+        HexPosition { q, r }
+    }
+
+    // From cube coordinates, which have to add up to 0
+    pub fn from_cube(q: isize, r: isize, s: isize) -> HexPosition {
+        assert_eq!(q + r + s, 0, "Cube coordinates have to add up to 0");
+        HexPosition { q, r }
+    }
+
+    pub fn q(&self) -> isize {
+        self.q
+    }
+
+    pub fn r(&self) -> isize {
+        self.r
+    }
+
+    pub fn s(&self) -> isize {
+        -self.q - self.r
+    }
+
+    pub fn step(&mut self, dq: isize, dr: isize) {
+        self.q += dq;
+        self.r += dr;
+    }
+
+    pub fn neighbors(&self) -> [HexPosition; 6] {
+        HEX_DIRECTIONS.map(|(dq, dr)| HexPosition::new(self.q + dq, self.r + dr))
+    }
+
+    // Number of steps between the positions
+    pub fn distance(&self, other: &HexPosition) -> usize {
+        let dq = (self.q - other.q).unsigned_abs();
+        let dr = (self.r - other.r).unsigned_abs();
+        let ds = (self.s() - other.s()).unsigned_abs();
+        dq.max(dr).max(ds)
+    }
+}
+
+// The shape of a HexBoard
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum HexLayout {
+    // A parallelogram with q in 0..width and r in 0..height, like the Hex board
+    Rhombus { width: usize, height: usize },
+    // A hexagon of all positions with a distance up to radius from (0, 0), like Havannah
+    // and Abalone
+    Hexagon { radius: usize },
+}
+
+impl HexLayout {
+    pub fn contains(&self, pos: &HexPosition) -> bool {
+        match self {
+            HexLayout::Rhombus { width, height } => {
+                pos.q >= 0 && pos.r >= 0 && pos.q < *width as isize && pos.r < *height as isize
+            }
+            HexLayout::Hexagon { radius } => pos.distance(&HexPosition::new(0, 0)) <= *radius,
+        }
+    }
+
+    // The smallest q and r, and the size of the rhombus containing the layout
+    fn bounds(&self) -> (isize, isize, usize, usize) {
+        match self {
+            HexLayout::Rhombus { width, height } => (0, 0, *width, *height),
+            HexLayout::Hexagon { radius } => {
+                let r = *radius as isize;
+                (-r, -r, 2 * radius + 1, 2 * radius + 1)
+            }
+        }
+    }
+}
+
+pub struct HexBoard<FieldContent> {
+    layout: HexLayout,
+    // The bounding rhombus, fields outside of the layout are never used
+    fields: Vec<FieldContent>,
+}
+
+impl<FieldContent> HexBoard<FieldContent> {
+    pub fn new(layout: HexLayout, default_content: fn() -> FieldContent) -> HexBoard<FieldContent> {
+        let (_, _, width, height) = layout.bounds();
+        HexBoard {
+            layout,
+            fields: std::iter::repeat_with(default_content)
+                .take(width * height)
+                .collect(),
+        }
+    }
+
+    pub fn layout(&self) -> HexLayout {
+        self.layout
+    }
+
+    pub fn contains(&self, pos: &HexPosition) -> bool {
+        self.layout.contains(pos)
+    }
+
+    fn index(&self, pos: &HexPosition) -> Option<usize> {
+        if !self.layout.contains(pos) {
+            return None;
+        }
+        let (min_q, min_r, width, _) = self.layout.bounds();
+        Some((pos.q - min_q) as usize + (pos.r - min_r) as usize * width)
+    }
+
+    // Set the content of a field
+    pub fn set(&mut self, pos: &HexPosition, value: FieldContent) {
+        if let Some(index) = self.index(pos) {
+            self.fields[index] = value;
+        } else {
+            panic!("Invalid insert position");
+        }
+    }
+
+    // Get the content of the specific field
+    pub fn get(&self, pos: &HexPosition) -> Option<&FieldContent> {
+        self.index(pos).map(|i| &self.fields[i])
+    }
+
+    // All positions of the board, row by row
+    pub fn positions(&self) -> Vec<HexPosition> {
+        let (min_q, min_r, width, height) = self.layout.bounds();
+        let mut positions = Vec::new();
+        for r in min_r..min_r + height as isize {
+            for q in min_q..min_q + width as isize {
+                let pos = HexPosition::new(q, r);
+                if self.layout.contains(&pos) {
+                    positions.push(pos);
+                }
+            }
+        }
+        positions
+    }
+
+    // The neighbors of the position, that are on the board
+    pub fn neighbors(&self, pos: &HexPosition) -> Vec<HexPosition> {
+        pos.neighbors()
+            .into_iter()
+            .filter(|n| self.layout.contains(n))
+            .collect()
+    }
+
+    // Iterates from the position in the direction (dq, dr), until it leaves the board
+    pub fn field_iterator(
+        &self,
+        pos: HexPosition,
+        dq: isize,
+        dr: isize,
+    ) -> HexFieldIterator<'_, FieldContent> {
+        HexFieldIterator {
+            pos,
+            dq,
+            dr,
+            board: self,
+        }
+    }
+}
+
+pub struct HexFieldIterator<'a, FieldContent> {
+    pos: HexPosition,
+    dq: isize,
+    dr: isize,
+    board: &'a HexBoard<FieldContent>,
+}
+
+impl<'a, FieldContent> Iterator for HexFieldIterator<'a, FieldContent> {
+    type Item = &'a FieldContent;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let result = self.board.get(&self.pos);
+        self.pos.step(self.dq, self.dr);
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn cube_coordinates() {
+        // Setup
+        let p = HexPosition::from_cube(2, -3, 1);
+
+        // Test
+        assert_eq!((p.q(), p.r(), p.s()), (2, -3, 1));
+    }
+
+    #[test]
+    fn distance() {
+        let origin = HexPosition::new(0, 0);
+        assert_eq!(origin.distance(&origin), 0);
+        assert_eq!(origin.distance(&HexPosition::new(1, -1)), 1);
+        assert_eq!(origin.distance(&HexPosition::new(2, 1)), 3);
+        assert_eq!(
+            HexPosition::new(-2, 3).distance(&HexPosition::new(1, -1)),
+            4
+        );
+        for n in origin.neighbors() {
+            assert_eq!(origin.distance(&n), 1);
+        }
+    }
+
+    #[test]
+    fn hexagon_layout() {
+        // Setup
+        let board = HexBoard::new(HexLayout::Hexagon { radius: 2 }, || 0);
+
+        // Test
+        assert_eq!(board.positions().len(), 19);
+        assert_eq!(board.neighbors(&HexPosition::new(0, 0)).len(), 6);
+        // A corner
+        assert_eq!(board.neighbors(&HexPosition::new(2, 0)).len(), 3);
+        // An edge
+        assert_eq!(board.neighbors(&HexPosition::new(2, -1)).len(), 4);
+        assert_eq!(board.get(&HexPosition::new(2, 1)), None);
+    }
+
+    #[test]
+    fn rhombus_layout() {
+        // Setup
+        let board = HexBoard::new(
+            HexLayout::Rhombus {
+                width: 11,
+                height: 11,
+            },
+            || 0,
+        );
+
+        // Test
+        assert_eq!(board.positions().len(), 121);
+        // The acute and the obtuse corner
+        assert_eq!(board.neighbors(&HexPosition::new(0, 0)).len(), 2);
+        assert_eq!(board.neighbors(&HexPosition::new(10, 0)).len(), 3);
+    }
+
+    #[test]
+    fn field_iterator() {
+        // Setup
+        let mut board = HexBoard::new(HexLayout::Hexagon { radius: 3 }, || 0);
+        for i in 0..4 {
+            board.set(&HexPosition::new(-i, i), i);
+        }
+
+        // Act
+        let fields: Vec<&isize> = board
+            .field_iterator(HexPosition::new(3, -3), -1, 1)
+            .collect();
+
+        // Test
+        assert_eq!(fields, vec![&0, &0, &0, &0, &1, &2, &3]);
+    }
+}
@@ -1 +1,6 @@
+pub mod bitboard;
+pub mod grid_3d;
 pub mod grid_board;
+pub mod hex_board;
+pub mod random;
+pub mod stacked_board;
@@ -0,0 +1,114 @@
+/*! A small, seedable pseudo random number generator (SplitMix64,
+ * <https://prng.di.unimi.it/splitmix64.c>).
+ *
+ * Its sequence only depends on the seed, so matches and experiments can be reproduced.
+ */
+
+#[derive(Clone, Debug)]
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Random {
+        Random { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// A number in 0..n, n must not be 0
+    pub fn below(&mut self, n: u64) -> u64 {
+        ((u128::from(self.next_u64()) * u128::from(n)) >> 64) as u64
+    }
+
+    /// A number in [0, 1)
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /** Picks an index with a probability proportional to its weight, `None` if all weights
+     * are 0. Panics if the weights sum up to more than `u64::MAX`.
+     */
+    pub fn weighted_index(&mut self, weights: &[u64]) -> Option<usize> {
+        let total = weights
+            .iter()
+            .try_fold(0u64, |total, weight| total.checked_add(*weight))
+            .expect("The weights sum up to more than u64::MAX");
+        if total == 0 {
+            return None;
+        }
+        let mut pick = self.below(total);
+        for (index, weight) in weights.iter().enumerate() {
+            if pick < *weight {
+                return Some(index);
+            }
+            pick -= weight;
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_sequence() {
+        // Setup
+        let mut r1 = Random::new(42);
+        let mut r2 = Random::new(42);
+        let mut r3 = Random::new(43);
+
+        // Act
+        let s1: Vec<u64> = (0..10).map(|_| r1.next_u64()).collect();
+        let s2: Vec<u64> = (0..10).map(|_| r2.next_u64()).collect();
+        let s3: Vec<u64> = (0..10).map(|_| r3.next_u64()).collect();
+
+        // Test
+        assert_eq!(s1, s2);
+        assert_ne!(s1, s3);
+    }
+
+    #[test]
+    fn known_splitmix_values() {
+        let mut r = Random::new(1234567);
+        assert_eq!(r.next_u64(), 6457827717110365317);
+        assert_eq!(r.next_u64(), 3203168211198807973);
+    }
+
+    #[test]
+    fn values_in_range() {
+        let mut r = Random::new(7);
+        for _ in 0..1000 {
+            assert!(r.below(6) < 6);
+            let f = r.next_f64();
+            assert!((0.0..1.0).contains(&f));
+        }
+    }
+
+    #[test]
+    fn weighted_index_respects_weights() {
+        let mut r = Random::new(7);
+        assert_eq!(r.weighted_index(&[0, 0]), None);
+        for _ in 0..100 {
+            assert_eq!(r.weighted_index(&[0, 5, 0]), Some(1));
+        }
+        let picks: Vec<usize> = (0..1000)
+            .map(|_| r.weighted_index(&[1, 3]).unwrap())
+            .collect();
+        let ones = picks.iter().filter(|i| **i == 1).count();
+        assert!(ones > 650 && ones < 850);
+    }
+
+    #[test]
+    #[should_panic(expected = "The weights sum up to more than u64::MAX")]
+    fn weighted_index_rejects_overflowing_weights() {
+        Random::new(7).weighted_index(&[u64::MAX, 1]);
+    }
+}
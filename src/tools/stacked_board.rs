@@ -0,0 +1,113 @@
+// A grid board with a stack of pieces on every field, for games like Tak or Focus. Moves are
+// made of push, pop and move_stack, which all have an exact inverse, so reverse_move can undo
+// them in the opposite order.
+use crate::tools::grid_board::{Board, BoardPosition};
+
+pub struct StackedBoard<Piece> {
+    board: Board<Vec<Piece>>,
+}
+
+impl<Piece> StackedBoard<Piece> {
+    pub fn new(rows: usize, cols: usize) -> StackedBoard<Piece> {
+        StackedBoard {
+            board: Board::new(rows, cols, Vec::new),
+        }
+    }
+
+    pub fn rows(&self) -> usize {
+        self.board.rows()
+    }
+
+    pub fn columns(&self) -> usize {
+        self.board.columns()
+    }
+
+    // The underlying board of stacks, e.g. for its iterators
+    pub fn board(&self) -> &Board<Vec<Piece>> {
+        &self.board
+    }
+
+    fn stack_mut(&mut self, pos: &BoardPosition) -> &mut Vec<Piece> {
+        self.board.get_mut(pos).expect("Invalid position")
+    }
+
+    // The pieces on the field, from the bottom to the top. Empty for invalid positions.
+    pub fn stack(&self, pos: &BoardPosition) -> &[Piece] {
+        self.board.get(pos).map(|s| s.as_slice()).unwrap_or(&[])
+    }
+
+    pub fn height(&self, pos: &BoardPosition) -> usize {
+        self.stack(pos).len()
+    }
+
+    pub fn top(&self, pos: &BoardPosition) -> Option<&Piece> {
+        self.stack(pos).last()
+    }
+
+    // Puts the piece on top of the stack, panics if the position is not on the board
+    pub fn push(&mut self, pos: &BoardPosition, piece: Piece) {
+        self.stack_mut(pos).push(piece);
+    }
+
+    // Takes the top piece from the stack
+    pub fn pop(&mut self, pos: &BoardPosition) -> Option<Piece> {
+        self.board.get_mut(pos)?.pop()
+    }
+
+    // Moves the top count pieces from one stack on top of another, keeping their order. Undone
+    // by moving count pieces back. Panics if there are less than count pieces.
+    pub fn move_stack(&mut self, from: &BoardPosition, to: &BoardPosition, count: usize) {
+        let source = self.stack_mut(from);
+        assert!(count <= source.len(), "Not enough pieces on the stack");
+        let pieces = source.split_off(source.len() - count);
+        self.stack_mut(to).extend(pieces);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn push_and_pop() {
+        // Setup
+        let mut board = StackedBoard::new(5, 5);
+        let pos = BoardPosition::new(2, 3);
+
+        // Act
+        board.push(&pos, 'a');
+        board.push(&pos, 'b');
+
+        // Test
+        assert_eq!(board.stack(&pos), &['a', 'b']);
+        assert_eq!(board.top(&pos), Some(&'b'));
+        assert_eq!(board.pop(&pos), Some('b'));
+        assert_eq!(board.height(&pos), 1);
+        assert_eq!(board.pop(&BoardPosition::new(0, 0)), None);
+        assert_eq!(board.height(&BoardPosition::new(9, 9)), 0);
+    }
+
+    #[test]
+    fn move_stack_and_undo() {
+        // Setup
+        let mut board = StackedBoard::new(3, 3);
+        let from = BoardPosition::new(0, 0);
+        let to = BoardPosition::new(1, 0);
+        for piece in [1, 2, 3] {
+            board.push(&from, piece);
+        }
+        board.push(&to, 9);
+
+        // Act
+        board.move_stack(&from, &to, 2);
+
+        // Test
+        assert_eq!(board.stack(&from), &[1]);
+        assert_eq!(board.stack(&to), &[9, 2, 3]);
+
+        // Undo
+        board.move_stack(&to, &from, 2);
+        assert_eq!(board.stack(&from), &[1, 2, 3]);
+        assert_eq!(board.stack(&to), &[9]);
+    }
+}
@@ -1,20 +1,86 @@
 use std::time::Duration;
-use crate::model::{MiniMaxGameState, MoveOutcome, Player};
-use crate::negamax::negamax;
+use crate::model::{GameState, MiniMaxGameState, MoveIterator, MoveOutcome, Player};
+use crate::negamax::{negamax, negamax_with_random_ties};
+use crate::opening_book::BookProbe;
+use crate::tools::random::Random;
 
 #[derive(Debug)]
 pub struct Statistics {
     pub total_negamax_duration: Duration,
+    pub book_moves: usize,
+    pub random_moves: usize,
 }
 
 impl Statistics {
     pub fn new() -> Statistics {
         Statistics {
             total_negamax_duration: Duration::from_secs(0),
+            book_moves: 0,
+            random_moves: 0,
         }
     }
 }
 
+/// Settings for [ki_battle_with_options]
+pub struct MatchOptions<'a, GS1: GameState, GS2: GameState> {
+    pub ki1_depth: i8,
+    pub ki2_depth: i8,
+    /// Opening book consulted by ki1 before searching
+    pub ki1_book: Option<&'a dyn BookProbe<GS1>>,
+    /// Opening book consulted by ki2 before searching
+    pub ki2_book: Option<&'a dyn BookProbe<GS2>>,
+    /// Seed for all random decisions, like choosing between book moves
+    pub seed: u64,
+    /// Choose at random between equally scored moves, instead of the first one
+    pub random_tie_breaks: bool,
+    /// Number of plies at the start of the game, that are played at random by both players
+    pub random_opening_plies: usize,
+}
+
+impl<'a, GS1: GameState, GS2: GameState> MatchOptions<'a, GS1, GS2> {
+    pub fn new(ki1_depth: i8, ki2_depth: i8) -> MatchOptions<'a, GS1, GS2> {
+        MatchOptions {
+            ki1_depth,
+            ki2_depth,
+            ki1_book: None,
+            ki2_book: None,
+            seed: 0,
+            random_tie_breaks: false,
+            random_opening_plies: 0,
+        }
+    }
+}
+
+pub struct MatchResult<M> {
+    pub winner: Option<Player>,
+    pub ki1_statistics: Statistics,
+    pub ki2_statistics: Statistics,
+    /// All moves of the game, as moves of the first game state
+    pub moves: Vec<M>,
+}
+
+/// Replays the moves of a game, until it is over. Every position after the first `skip_plies`
+/// plies is visited before its move is applied. The game state is returned to the initial state.
+pub fn replay_game<GS, F>(gs: &mut GS, moves: &[GS::Move], skip_plies: usize, mut visit: F)
+where
+    GS: GameState,
+    F: FnMut(&GS),
+{
+    let mut applied = 0;
+    for m in moves {
+        if applied >= skip_plies {
+            visit(gs);
+        }
+        applied += 1;
+        if let MoveOutcome::PlayerWon(_) | MoveOutcome::Tie = gs.apply_move(m) {
+            break;
+        }
+    }
+    for m in moves[..applied].iter().rev() {
+        gs.reverse_move(m);
+    }
+}
+
 pub fn ki_battle<GS1, GS2, F2To1, F1To2>(
     gs1: &mut GS1,
     gs2: &mut GS2,
@@ -23,6 +89,32 @@ pub fn ki_battle<GS1, GS2, F2To1, F1To2>(
     find_gs1_move: F2To1,
     find_gs2_move: F1To2,
 ) -> (Option<Player>, Statistics, Statistics)
+where
+    GS1: MiniMaxGameState,
+    GS2: MiniMaxGameState,
+    F1To2: Fn(&GS1::Move, &GS2) -> Option<GS2::Move>,
+    F2To1: Fn(&GS2::Move, &GS1) -> Option<GS1::Move>,
+{
+    let result = ki_battle_with_options(
+        gs1,
+        gs2,
+        find_gs1_move,
+        find_gs2_move,
+        &MatchOptions::new(ki1_depth, ki2_depth),
+    );
+    (result.winner, result.ki1_statistics, result.ki2_statistics)
+}
+
+/** Same as [ki_battle], with more settings. It also records the moves of the game, for example
+ * to build an opening book from them.
+ */
+pub fn ki_battle_with_options<GS1, GS2, F2To1, F1To2>(
+    gs1: &mut GS1,
+    gs2: &mut GS2,
+    find_gs1_move: F2To1,
+    find_gs2_move: F1To2,
+    options: &MatchOptions<GS1, GS2>,
+) -> MatchResult<GS1::Move>
 where
     GS1: MiniMaxGameState,
     GS2: MiniMaxGameState,
@@ -32,25 +124,43 @@ where
     let mut active_player = Player::Player1;
     let mut p1_statistics = Statistics::new();
     let mut p2_statistics = Statistics::new();
+    let mut random = Random::new(options.seed);
+    let mut moves = Vec::new();
     loop {
         let outcome = match active_player {
             Player::Player1 => {
-                let start = std::time::Instant::now();
-                let (_s, m) = negamax(gs1, ki1_depth);
-                p1_statistics.total_negamax_duration += start.elapsed();
+                let m = choose_move(
+                    gs1,
+                    options.ki1_depth,
+                    options.ki1_book,
+                    moves.len() < options.random_opening_plies,
+                    options.random_tie_breaks,
+                    &mut random,
+                    &mut p1_statistics,
+                );
                 if let Some(the_move) = m {
-                    gs2.apply_move(&find_gs2_move(&the_move, &gs2).unwrap());
-                    gs1.apply_move(&the_move)
+                    gs2.apply_move(&find_gs2_move(&the_move, gs2).unwrap());
+                    let outcome = gs1.apply_move(&the_move);
+                    moves.push(the_move);
+                    outcome
                 } else {
                     panic!("No move found!");
                 }
             }
             Player::Player2 => {
-                let start = std::time::Instant::now();
-                let (_s, m) = negamax(gs2, ki2_depth);
-                p2_statistics.total_negamax_duration += start.elapsed();
+                let m = choose_move(
+                    gs2,
+                    options.ki2_depth,
+                    options.ki2_book,
+                    moves.len() < options.random_opening_plies,
+                    options.random_tie_breaks,
+                    &mut random,
+                    &mut p2_statistics,
+                );
                 if let Some(the_move) = m {
-                    gs1.apply_move(&find_gs1_move(&the_move, &gs1).unwrap());
+                    let gs1_move = find_gs1_move(&the_move, gs1).unwrap();
+                    gs1.apply_move(&gs1_move);
+                    moves.push(gs1_move);
                     gs2.apply_move(&the_move)
                 } else {
                     panic!("No move found!");
@@ -59,10 +169,20 @@ where
         };
         match outcome {
             MoveOutcome::PlayerWon(p) => {
-                return (Some(p), p1_statistics, p2_statistics);
+                return MatchResult {
+                    winner: Some(p),
+                    ki1_statistics: p1_statistics,
+                    ki2_statistics: p2_statistics,
+                    moves,
+                };
             }
             MoveOutcome::Tie => {
-                return (None, p1_statistics, p2_statistics);
+                return MatchResult {
+                    winner: None,
+                    ki1_statistics: p1_statistics,
+                    ki2_statistics: p2_statistics,
+                    moves,
+                };
             }
             MoveOutcome::SwitchPlayer(_) => {
                 active_player = active_player.other();
@@ -71,3 +191,121 @@ where
         }
     }
 }
+
+/// The move of one ki: a random opening move, a book move or the result of the search.
+fn choose_move<GS: MiniMaxGameState>(
+    gs: &mut GS,
+    depth: i8,
+    book: Option<&dyn BookProbe<GS>>,
+    random_opening: bool,
+    random_tie_breaks: bool,
+    random: &mut Random,
+    statistics: &mut Statistics,
+) -> Option<GS::Move> {
+    if random_opening {
+        let mut moves = Vec::new();
+        let mut move_iterator = gs.move_iterator();
+        while let Some(m) = move_iterator.next(gs) {
+            moves.push(m.clone());
+        }
+        if !moves.is_empty() {
+            statistics.random_moves += 1;
+            return Some(moves.swap_remove(random.below(moves.len() as u64) as usize));
+        }
+    }
+    if let Some(m) = book.and_then(|book| book.book_move(gs, random)) {
+        statistics.book_moves += 1;
+        return Some(m);
+    }
+    let start = std::time::Instant::now();
+    let (_s, m) = if random_tie_breaks {
+        negamax_with_random_ties(gs, depth, random)
+    } else {
+        negamax(gs, depth)
+    };
+    statistics.total_negamax_duration += start.elapsed();
+    m
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::opening_book::{BookPosition, OpeningBook};
+    use crate::testing::test_games::NimGameState;
+
+    fn same_move(m: &u32, _gs: &NimGameState) -> Option<u32> {
+        Some(*m)
+    }
+
+    #[test]
+    fn record_moves_of_the_game() {
+        // Setup
+        let mut gs1 = NimGameState::new(5);
+        let mut gs2 = NimGameState::new(5);
+
+        // Act
+        let result = ki_battle_with_options(
+            &mut gs1,
+            &mut gs2,
+            same_move,
+            same_move,
+            &MatchOptions::new(6, 6),
+        );
+
+        // Test
+        // 5 stones is a win for the first player
+        assert_eq!(result.winner, Some(Player::Player1));
+        assert_eq!(result.moves.iter().sum::<u32>(), 5);
+        assert_eq!(result.ki1_statistics.book_moves, 0);
+    }
+
+    #[test]
+    fn play_book_moves_first() {
+        // Setup
+        let mut gs1 = NimGameState::new(5);
+        let mut gs2 = NimGameState::new(5);
+        let mut book = OpeningBook::new();
+        // A bad move: leaves 4 stones for the opponent, instead of 3
+        book.add(gs1.position_hash(), "take1", 1);
+        let mut options = MatchOptions::new(6, 6);
+        options.ki1_book = Some(&book);
+
+        // Act
+        let result = ki_battle_with_options(&mut gs1, &mut gs2, same_move, same_move, &options);
+
+        // Test
+        assert_eq!(result.moves[0], 1);
+        assert_eq!(result.winner, Some(Player::Player2));
+        assert_eq!(result.ki1_statistics.book_moves, 1);
+        assert_eq!(result.ki2_statistics.book_moves, 0);
+    }
+
+    #[test]
+    fn random_openings_are_reproducible() {
+        // Setup
+        let mut options = MatchOptions::new(2, 2);
+        options.random_opening_plies = 6;
+        options.random_tie_breaks = true;
+        let play = |options: &MatchOptions<NimGameState, NimGameState>| {
+            let mut gs1 = NimGameState::new(20);
+            let mut gs2 = NimGameState::new(20);
+            ki_battle_with_options(&mut gs1, &mut gs2, same_move, same_move, options)
+        };
+
+        // Act
+        let games: Vec<Vec<u32>> = (0..10)
+            .map(|seed| {
+                options.seed = seed;
+                play(&options).moves
+            })
+            .collect();
+
+        // Test
+        options.seed = 3;
+        let result = play(&options);
+        assert_eq!(result.moves, games[3]);
+        assert_eq!(result.ki1_statistics.random_moves, 3);
+        assert_eq!(result.ki2_statistics.random_moves, 3);
+        assert!(games.iter().any(|g| *g != games[0]));
+    }
+}
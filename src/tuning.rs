@@ -0,0 +1,349 @@
+/*! Texel-style tuning of evaluation parameters (https://www.chessprogramming.org/Texel%27s_Tuning_Method).
+ *
+ * An evaluation, that is a weighted sum of features, exposes its weights as named integer
+ * [Parameters] and the features of a position via [Tunable]. Positions of recorded games are
+ * collected in [TuningData], labeled with the final result of the game. The [Tuner] then
+ * changes the parameters, so that the evaluation predicts the results as well as possible.
+ */
+use crate::model::{GameState, Player};
+use crate::tournament::{replay_game, MatchResult};
+use std::fmt;
+
+/** Named integer parameters of an evaluation */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Parameters {
+    pub names: Vec<String>,
+    pub values: Vec<i64>,
+}
+
+impl Parameters {
+    pub fn new(parameters: &[(&str, i64)]) -> Parameters {
+        Parameters {
+            names: parameters
+                .iter()
+                .map(|(name, _)| name.to_string())
+                .collect(),
+            values: parameters.iter().map(|(_, value)| *value).collect(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn get(&self, name: &str) -> Option<i64> {
+        self.names
+            .iter()
+            .position(|n| n == name)
+            .map(|index| self.values[index])
+    }
+
+    /// The weighted sum of the features
+    pub fn evaluate(&self, features: &[i64]) -> i64 {
+        self.values
+            .iter()
+            .zip(features)
+            .map(|(value, feature)| value * feature)
+            .sum()
+    }
+}
+
+impl fmt::Display for Parameters {
+    /// One `name = value` line per parameter
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, value) in self.names.iter().zip(&self.values) {
+            writeln!(f, "{} = {}", name, value)?;
+        }
+        Ok(())
+    }
+}
+
+/** A game state with an evaluation, that is the weighted sum of features */
+pub trait Tunable: GameState {
+    /** The parameters currently used by the evaluation, one for each feature */
+    fn parameters(&self) -> Parameters;
+
+    /** Uses the parameters for the evaluation from now on, e.g. the result of [Tuner::tune] */
+    fn set_parameters(&mut self, parameters: &Parameters);
+
+    /** The features of the position, from the view of the given player. With the current
+     * parameters, the weighted sum should be the evaluation for the player.
+     */
+    fn features(&self, player: &Player) -> Vec<i64>;
+}
+
+/** Positions of recorded games with the results of the games */
+pub struct TuningData {
+    /// Plies at the start of each game, that are not used
+    pub skip_plies: usize,
+    // Features for Player1 and the result for Player1: 1 win, 0.5 tie, 0 loss
+    positions: Vec<(Vec<i64>, f64)>,
+}
+
+impl TuningData {
+    pub fn new() -> TuningData {
+        TuningData {
+            skip_plies: 0,
+            positions: Vec::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    /// Adds the positions of a game, given by its initial state, its moves and the winner
+    /// (`None` for a tie). The game state is returned to the initial state.
+    pub fn add_game<GS: Tunable>(
+        &mut self,
+        initial: &mut GS,
+        moves: &[GS::Move],
+        winner: Option<Player>,
+    ) {
+        let result = match winner {
+            Some(Player::Player1) => 1.0,
+            Some(Player::Player2) => 0.0,
+            None => 0.5,
+        };
+        replay_game(initial, moves, self.skip_plies, |gs| {
+            self.positions.push((gs.features(&Player::Player1), result));
+        });
+    }
+
+    /// Adds the positions of a game played by [crate::tournament::ki_battle_with_options]
+    pub fn add_match_result<GS: Tunable>(
+        &mut self,
+        initial: &mut GS,
+        result: &MatchResult<GS::Move>,
+    ) {
+        self.add_game(initial, &result.moves, result.winner);
+    }
+}
+
+impl Default for TuningData {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/** Fits parameters to [TuningData] with a local search.
+ *
+ * The error is the mean squared difference between the game results and the win probability
+ * predicted from the evaluation `e` as `1 / (1 + 10^(-scaling * e / 400))`.
+ */
+pub struct Tuner {
+    /// Scaling between evaluation and win probability, see [Tuner::fit_scaling]
+    pub scaling: f64,
+    /// Maximal number of passes over all parameters
+    pub max_rounds: usize,
+}
+
+impl Tuner {
+    pub fn new() -> Tuner {
+        Tuner {
+            scaling: 1.0,
+            max_rounds: 100,
+        }
+    }
+
+    pub fn error(&self, data: &TuningData, parameters: &Parameters) -> f64 {
+        if data.is_empty() {
+            return 0.0;
+        }
+        let total: f64 = data
+            .positions
+            .iter()
+            .map(|(features, result)| {
+                let evaluation = parameters.evaluate(features) as f64;
+                let prediction = 1.0 / (1.0 + 10f64.powf(-self.scaling * evaluation / 400.0));
+                (result - prediction) * (result - prediction)
+            })
+            .sum();
+        total / data.len() as f64
+    }
+
+    /// Sets the scaling that minimizes the error of the given parameters
+    pub fn fit_scaling(&mut self, data: &TuningData, parameters: &Parameters) {
+        let mut step = self.scaling;
+        let mut best_error = self.error(data, parameters);
+        // Search in both directions, with shrinking steps, while the scaling stays positive
+        for _ in 0..64 {
+            let start = self.scaling;
+            for candidate in [start * 2f64.powf(step), start / 2f64.powf(step)] {
+                self.scaling = candidate;
+                let error = self.error(data, parameters);
+                if error < best_error {
+                    best_error = error;
+                    break;
+                }
+                self.scaling = start;
+            }
+            if self.scaling == start {
+                step /= 2.0;
+            }
+        }
+    }
+
+    /// Improves the parameters until no single parameter change lowers the error. Every
+    /// parameter is changed in steps, that start at a quarter of its value and are halved,
+    /// when neither direction helps.
+    pub fn tune(&self, data: &TuningData, parameters: &Parameters) -> Parameters {
+        let mut parameters = parameters.clone();
+        let mut steps: Vec<i64> = parameters
+            .values
+            .iter()
+            .map(|v| (v.abs() / 4).max(1))
+            .collect();
+        let mut best_error = self.error(data, &parameters);
+        for _ in 0..self.max_rounds {
+            let mut improved = false;
+            for (index, step) in steps.iter_mut().enumerate() {
+                let start = parameters.values[index];
+                for candidate in [start + *step, start - *step] {
+                    parameters.values[index] = candidate;
+                    let error = self.error(data, &parameters);
+                    if error < best_error {
+                        best_error = error;
+                        improved = true;
+                        break;
+                    }
+                    parameters.values[index] = start;
+                }
+                if parameters.values[index] == start && *step > 1 {
+                    *step /= 2;
+                    improved = true;
+                }
+            }
+            if !improved {
+                break;
+            }
+        }
+        parameters
+    }
+}
+
+impl Default for Tuner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::model::MoveOutcome;
+    use crate::testing::test_games::NimGameState;
+
+    // Nim with a made up evaluation: stones modulo 3 and the stones themselves
+    impl Tunable for NimGameState {
+        fn parameters(&self) -> Parameters {
+            Parameters::new(&[("winning", 1), ("stones", 0)])
+        }
+
+        // The made up evaluation is fixed
+        fn set_parameters(&mut self, _parameters: &Parameters) {}
+
+        fn features(&self, player: &Player) -> Vec<i64> {
+            let winning = if self.stones % 3 == 0 { -1 } else { 1 };
+            let sign = if *player == self.active_player() {
+                1
+            } else {
+                -1
+            };
+            vec![sign * winning, sign * self.stones as i64]
+        }
+    }
+
+    fn perfect_game(stones: u32) -> (Vec<u32>, Option<Player>) {
+        let mut gs = NimGameState::new(stones);
+        let mut moves = Vec::new();
+        loop {
+            let m = if gs.stones % 3 == 0 { 1 } else { gs.stones % 3 };
+            moves.push(m);
+            if let MoveOutcome::PlayerWon(p) = gs.apply_move(&m) {
+                return (moves, Some(p));
+            }
+        }
+    }
+
+    #[test]
+    fn parameters() {
+        // Setup
+        let parameters = Parameters::new(&[("a", 2), ("b", -3)]);
+
+        // Test
+        assert_eq!(parameters.len(), 2);
+        assert_eq!(parameters.get("b"), Some(-3));
+        assert_eq!(parameters.get("c"), None);
+        assert_eq!(parameters.evaluate(&[5, 1]), 7);
+        assert_eq!(parameters.to_string(), "a = 2\nb = -3\n");
+    }
+
+    #[test]
+    fn add_game() {
+        // Setup
+        let mut data = TuningData::new();
+        data.skip_plies = 1;
+        let mut gs = NimGameState::new(5);
+        let (moves, winner) = perfect_game(5);
+
+        // Act
+        data.add_game(&mut gs, &moves, winner);
+
+        // Test
+        // 5 -> 3 -> 2 -> 0, the first position is skipped
+        assert_eq!(data.len(), 2);
+        assert_eq!(gs, NimGameState::new(5));
+        assert_eq!(data.positions[0], (vec![1, -3], 1.0));
+    }
+
+    fn perfect_games() -> TuningData {
+        let mut data = TuningData::new();
+        for stones in 4..20 {
+            let (moves, winner) = perfect_game(stones);
+            data.add_game(&mut NimGameState::new(stones), &moves, winner);
+        }
+        data
+    }
+
+    #[test]
+    fn fit_scaling() {
+        // Setup
+        let data = perfect_games();
+        let mut tuner = Tuner::new();
+        let parameters = NimGameState::new(1).parameters();
+        let start_error = tuner.error(&data, &parameters);
+
+        // Act
+        tuner.fit_scaling(&data, &parameters);
+
+        // Test
+        assert!(tuner.scaling > 1.0);
+        assert!(tuner.error(&data, &parameters) < start_error);
+    }
+
+    #[test]
+    fn tuning_lowers_the_error() {
+        // Setup
+        let data = perfect_games();
+        let tuner = Tuner::new();
+        let start = NimGameState::new(1).parameters();
+        let start_error = tuner.error(&data, &start);
+
+        // Act
+        let tuned = tuner.tune(&data, &start);
+
+        // Test
+        // Perfect play: the player with a position not divisible by 3 always wins
+        assert!(tuner.error(&data, &tuned) < start_error);
+        assert!(tuned.get("winning").unwrap() > 1);
+    }
+}